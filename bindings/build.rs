@@ -1,6 +1,8 @@
 fn main() {
     windows::build! {
-        Windows::Graphics::SizeInt32,
+        Windows::Graphics::{SizeInt32, IGeometrySource2D},
+        Windows::Graphics::DirectX::{DirectXAlphaMode, DirectXPixelFormat},
+        Windows::Foundation::Size,
         Windows::Win32::System::WinRT::{
             CreateDispatcherQueueController, ICompositorDesktopInterop, RoInitialize,
         },
@@ -10,7 +12,12 @@ fn main() {
             CompositionColorBrush, CompositionContainerShape, CompositionEllipseGeometry,
             CompositionNineGridBrush, CompositionScopedBatch, CompositionShapeCollection,
             CompositionSpriteShape, Compositor, ShapeVisual, SpriteVisual,
-            Vector3KeyFrameAnimation, VisualCollection, ContainerVisual, CompositionRoundedRectangleGeometry
+            Vector3KeyFrameAnimation, VisualCollection, ContainerVisual, CompositionRoundedRectangleGeometry,
+            CompositionGraphicsDevice, CompositionDrawingSurface, CompositionSurfaceBrush,
+            CompositionClip, InsetClip, CompositionStretch, CompositionBrush, CompositionShadow,
+            DropShadow, CompositionGradientBrush, CompositionLinearGradientBrush,
+            CompositionColorGradientStop, CompositionColorGradientStopCollection,
+            CompositionMappingMode, CompositionPath, CompositionPathGeometry
         },
         Windows::Win32::Foundation::{
             RECT, BOOL, HINSTANCE, LRESULT, E_FAIL, E_HANDLE, HWND, WPARAM
@@ -22,10 +29,15 @@ fn main() {
             GetMessageW, PostQuitMessage, RegisterClassW, MSG, WNDCLASSW, TranslateMessage,
             CREATESTRUCTW, GetClientRect, AdjustWindowRectEx, ShowWindow, WINDOW_LONG_PTR_INDEX,
             SetWindowLongW, SetWindowLongPtrW, GetWindowLongW, GetWindowLongPtrW, PostQuitMessage, PeekMessageW,
-            PEEK_MESSAGE_REMOVE_TYPE, SetTimer, KillTimer
+            PEEK_MESSAGE_REMOVE_TYPE, SetTimer, KillTimer, WM_KEYDOWN, WM_KEYUP
         },
         Windows::Win32::System::LibraryLoader::GetModuleHandleW,
+        Windows::Win32::UI::KeyboardAndMouseInput::GetKeyState,
         Microsoft::Graphics::Canvas::CanvasDrawingSession,
+        Microsoft::Graphics::Canvas::CanvasBitmap,
+        Microsoft::Graphics::Canvas::Geometry::{
+            CanvasGeometry, CanvasPathBuilder, CanvasFigureLoop, CanvasSweepDirection, CanvasArcSize
+        },
         Microsoft::Graphics::Canvas::CanvasDevice,
         Microsoft::Graphics::Canvas::Text::*,
         Microsoft::Graphics::Canvas::UI::Composition::*,
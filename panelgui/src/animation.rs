@@ -0,0 +1,475 @@
+//! Animations independent of the composition backend. `Timeline` computes animated values
+//! for the time it's advanced to, so animations can be stepped deterministically
+
+use futures::channel::oneshot;
+
+/// Interpolation curve: maps linear progress 0..1 to eased progress
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    #[default]
+    EaseInOut,
+    /// Goes slightly beyond the target and comes back, for "pop" effects
+    EaseOutBack,
+    /// CSS-like cubic bezier with control points (x1, y1) and (x2, y2)
+    CubicBezier(f32, f32, f32, f32),
+}
+
+impl Easing {
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1. - (1. - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4. * t * t * t
+                } else {
+                    1. - (-2. * t + 2.).powi(3) / 2.
+                }
+            }
+            Easing::EaseOutBack => {
+                let c1 = 1.70158;
+                let c3 = c1 + 1.;
+                1. + c3 * (t - 1.).powi(3) + c1 * (t - 1.).powi(2)
+            }
+            Easing::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
+        }
+    }
+}
+
+fn bezier(p1: f32, p2: f32, s: f32) -> f32 {
+    let r = 1. - s;
+    3. * r * r * s * p1 + 3. * r * s * s * p2 + s * s * s
+}
+
+/// Finds the curve parameter for `x` by bisection and returns y at it
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
+    let (mut low, mut high) = (0., 1.);
+    let mut s = x;
+    for _ in 0..32 {
+        let value = bezier(x1, x2, s);
+        if (value - x).abs() < 1e-5 {
+            break;
+        }
+        if value < x {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.;
+    }
+    bezier(y1, y2, s)
+}
+
+/// Value which can be animated
+pub trait Lerp: Copy {
+    fn lerp(from: Self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(from: Self, to: Self, t: f32) -> Self {
+        from + (to - from) * t
+    }
+}
+
+impl Lerp for (f32, f32) {
+    fn lerp(from: Self, to: Self, t: f32) -> Self {
+        (f32::lerp(from.0, to.0, t), f32::lerp(from.1, to.1, t))
+    }
+}
+
+impl Lerp for (f32, f32, f32) {
+    fn lerp(from: Self, to: Self, t: f32) -> Self {
+        (
+            f32::lerp(from.0, to.0, t),
+            f32::lerp(from.1, to.1, t),
+            f32::lerp(from.2, to.2, t),
+        )
+    }
+}
+
+/// Timing of an animation. Times are in seconds
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transition {
+    pub duration: f32,
+    pub delay: f32,
+    pub easing: Easing,
+}
+
+impl Transition {
+    pub fn new(duration: f32, easing: Easing) -> Self {
+        Self {
+            duration,
+            delay: 0.,
+            easing,
+        }
+    }
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tween<T> {
+    pub from: T,
+    pub to: T,
+    pub transition: Transition,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(from: T, to: T, transition: Transition) -> Self {
+        Self {
+            from,
+            to,
+            transition,
+        }
+    }
+
+    /// Value at `elapsed` seconds after the tween was started, including the delay
+    pub fn value_at(&self, elapsed: f32) -> T {
+        let time = elapsed - self.transition.delay;
+        if time <= 0. {
+            self.from
+        } else if time >= self.transition.duration {
+            self.to
+        } else {
+            let t = self
+                .transition
+                .easing
+                .apply(time / self.transition.duration);
+            T::lerp(self.from, self.to, t)
+        }
+    }
+
+    pub fn is_finished(&self, elapsed: f32) -> bool {
+        elapsed >= self.transition.delay + self.transition.duration
+    }
+}
+
+pub type AnimationId = u64;
+
+struct Track<K, T> {
+    id: AnimationId,
+    key: K,
+    tween: Tween<T>,
+    start: f64,
+}
+
+/// Result of advancing the timeline: values to apply and animations which are over
+pub struct Step<K, T> {
+    pub values: Vec<(K, T)>,
+    /// Completed, fast-forwarded and replaced animations
+    pub finished: Vec<AnimationId>,
+}
+
+/// Set of running animations, one per key (e.g. per property of a visual)
+pub struct Timeline<K, T> {
+    /// Kept in f64 to not lose precision when the application runs for long
+    now: f64,
+    next_id: AnimationId,
+    tracks: Vec<Track<K, T>>,
+    replaced: Vec<AnimationId>,
+    waiters: Vec<(AnimationId, oneshot::Sender<()>)>,
+}
+
+impl<K: PartialEq + Clone, T: Lerp> Timeline<K, T> {
+    pub fn new() -> Self {
+        Self {
+            now: 0.,
+            next_id: 0,
+            tracks: Vec::new(),
+            replaced: Vec::new(),
+            waiters: Vec::new(),
+        }
+    }
+
+    pub fn now(&self) -> f64 {
+        self.now
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    pub fn is_running(&self, id: AnimationId) -> bool {
+        self.tracks.iter().any(|track| track.id == id)
+    }
+
+    /// Current value of the animation running for the key
+    pub fn value(&self, key: &K) -> Option<T> {
+        self.tracks
+            .iter()
+            .find(|track| track.key == *key)
+            .map(|track| track.tween.value_at((self.now - track.start) as f32))
+    }
+
+    /// Running animation for the key and its final value
+    pub fn target(&self, key: &K) -> Option<(AnimationId, T)> {
+        self.tracks
+            .iter()
+            .find(|track| track.key == *key)
+            .map(|track| (track.id, track.tween.to))
+    }
+
+    /// Starts the tween. The animation already running for the same key is replaced
+    pub fn start(&mut self, key: K, tween: Tween<T>) -> AnimationId {
+        let id = self.next_id;
+        self.next_id += 1;
+        if let Some(index) = self.tracks.iter().position(|track| track.key == key) {
+            let replaced = self.tracks.remove(index);
+            self.replaced.push(replaced.id);
+        }
+        self.tracks.push(Track {
+            id,
+            key,
+            tween,
+            start: self.now,
+        });
+        id
+    }
+
+    /// Moves time forward by `dt` seconds
+    pub fn advance(&mut self, dt: f32) -> Step<K, T> {
+        self.now += dt.max(0.) as f64;
+        let now = self.now;
+        let mut step = Step {
+            values: Vec::new(),
+            finished: std::mem::take(&mut self.replaced),
+        };
+        self.tracks.retain(|track| {
+            let elapsed = (now - track.start) as f32;
+            if elapsed >= track.tween.transition.delay {
+                step.values
+                    .push((track.key.clone(), track.tween.value_at(elapsed)));
+            }
+            let finished = track.tween.is_finished(elapsed);
+            if finished {
+                step.finished.push(track.id);
+            }
+            !finished
+        });
+        self.notify(&step.finished);
+        step
+    }
+
+    /// Jumps to the end of the animation
+    pub fn finish(&mut self, id: AnimationId) -> Step<K, T> {
+        let mut step = Step {
+            values: Vec::new(),
+            finished: Vec::new(),
+        };
+        if let Some(index) = self.tracks.iter().position(|track| track.id == id) {
+            let track = self.tracks.remove(index);
+            step.values.push((track.key, track.tween.to));
+            step.finished.push(track.id);
+        }
+        self.notify(&step.finished);
+        step
+    }
+
+    /// Jumps to the end of all animations
+    pub fn finish_all(&mut self) -> Step<K, T> {
+        let mut step = Step {
+            values: Vec::new(),
+            finished: std::mem::take(&mut self.replaced),
+        };
+        for track in self.tracks.drain(..) {
+            step.values.push((track.key, track.tween.to));
+            step.finished.push(track.id);
+        }
+        self.notify(&step.finished);
+        step
+    }
+
+    /// Receiver notified when the animation is over. For animations which are
+    /// not running anymore it's notified at once
+    pub fn finished(&mut self, id: AnimationId) -> oneshot::Receiver<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.waiters.retain(|(_, sender)| !sender.is_canceled());
+        if self.is_running(id) || self.replaced.contains(&id) {
+            self.waiters.push((id, sender));
+        } else {
+            let _ = sender.send(());
+        }
+        receiver
+    }
+
+    /// Waiters are notified when the step is made, so they are woken even if
+    /// applying its values fails
+    fn notify(&mut self, finished: &[AnimationId]) {
+        let (finished, waiting) = std::mem::take(&mut self.waiters)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(id, _)| finished.contains(id));
+        self.waiters = waiting;
+        for (_, sender) in finished {
+            let _ = sender.send(());
+        }
+    }
+}
+
+impl<K: PartialEq + Clone, T: Lerp> Default for Timeline<K, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::{Easing, Timeline, Transition, Tween};
+
+    fn linear(duration: f32) -> Transition {
+        Transition::new(duration, Easing::Linear)
+    }
+
+    #[test]
+    fn easing_endpoints() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+            Easing::EaseOutBack,
+            Easing::CubicBezier(0.25, 0.1, 0.25, 1.),
+        ] {
+            assert!(easing.apply(0.).abs() < 1e-4, "{:?} at 0", easing);
+            assert!((easing.apply(1.) - 1.).abs() < 1e-4, "{:?} at 1", easing);
+            let tween = Tween::new(10., 20., Transition::new(1., easing));
+            assert_eq!(tween.value_at(0.), 10.);
+            assert_eq!(tween.value_at(1.), 20.);
+        }
+    }
+
+    #[test]
+    fn advance_interpolates() {
+        let mut timeline = Timeline::new();
+        let id = timeline.start("x", Tween::new(0., 10., linear(1.)));
+        let step = timeline.advance(0.25);
+        assert_eq!(step.values, vec![("x", 2.5)]);
+        assert!(step.finished.is_empty());
+        assert_eq!(timeline.value(&"x"), Some(2.5));
+        let step = timeline.advance(1.);
+        assert_eq!(step.values, vec![("x", 10.)]);
+        assert_eq!(step.finished, vec![id]);
+        assert!(timeline.is_empty());
+        assert_eq!(timeline.value(&"x"), None);
+    }
+
+    #[test]
+    fn advance_waits_for_delay() {
+        let mut timeline = Timeline::new();
+        let id = timeline.start("x", Tween::new(0., 10., linear(1.).with_delay(0.5)));
+        let step = timeline.advance(0.25);
+        assert!(step.values.is_empty());
+        assert_eq!(timeline.value(&"x"), Some(0.));
+        let step = timeline.advance(0.75);
+        assert_eq!(step.values, vec![("x", 5.)]);
+        assert!(timeline.is_running(id));
+        let step = timeline.advance(0.5);
+        assert_eq!(step.values, vec![("x", 10.)]);
+        assert_eq!(step.finished, vec![id]);
+    }
+
+    #[test]
+    fn start_replaces_animation_of_same_key() {
+        let mut timeline = Timeline::new();
+        let first = timeline.start("x", Tween::new(0., 10., linear(1.)));
+        let other = timeline.start("y", Tween::new(0., 10., linear(1.)));
+        timeline.advance(0.5);
+        let second = timeline.start("x", Tween::new(5., 0., linear(1.)));
+        assert!(!timeline.is_running(first));
+        assert_eq!(timeline.target(&"x"), Some((second, 0.)));
+        let step = timeline.advance(0.5);
+        assert_eq!(step.finished, vec![first, other]);
+        assert_eq!(step.values, vec![("y", 10.), ("x", 2.5)]);
+    }
+
+    #[test]
+    fn finish_jumps_to_end() {
+        let mut timeline = Timeline::new();
+        let x = timeline.start("x", Tween::new(0., 10., linear(1.)));
+        let y = timeline.start("y", Tween::new(0., 20., linear(1.)));
+        let step = timeline.finish(x);
+        assert_eq!(step.values, vec![("x", 10.)]);
+        assert_eq!(step.finished, vec![x]);
+        assert!(timeline.is_running(y));
+        // Finishing an animation which is over does nothing
+        let step = timeline.finish(x);
+        assert!(step.values.is_empty() && step.finished.is_empty());
+    }
+
+    #[test]
+    fn finish_all_reports_replaced() {
+        let mut timeline = Timeline::new();
+        let first = timeline.start("x", Tween::new(0., 10., linear(1.)));
+        let second = timeline.start("x", Tween::new(0., 20., linear(1.)));
+        let y = timeline.start("y", Tween::new(0., 30., linear(1.)));
+        let step = timeline.finish_all();
+        assert_eq!(step.values, vec![("x", 20.), ("y", 30.)]);
+        assert_eq!(step.finished, vec![first, second, y]);
+        assert!(timeline.is_empty());
+    }
+
+    #[test]
+    fn waiters_notified_on_completion() {
+        let mut timeline = Timeline::new();
+        let id = timeline.start("x", Tween::new(0., 10., linear(1.)));
+        let mut finished = timeline.finished(id);
+        timeline.advance(0.5);
+        assert_eq!(finished.try_recv(), Ok(None));
+        timeline.advance(0.5);
+        assert_eq!(finished.now_or_never(), Some(Ok(())));
+    }
+
+    #[test]
+    fn waiters_notified_on_finish() {
+        let mut timeline = Timeline::new();
+        let x = timeline.start("x", Tween::new(0., 10., linear(1.)));
+        let y = timeline.start("y", Tween::new(0., 10., linear(1.)));
+        let (mut x_finished, mut y_finished) = (timeline.finished(x), timeline.finished(y));
+        timeline.finish(x);
+        assert_eq!(x_finished.try_recv(), Ok(Some(())));
+        assert_eq!(y_finished.try_recv(), Ok(None));
+        timeline.finish_all();
+        assert_eq!(y_finished.try_recv(), Ok(Some(())));
+    }
+
+    #[test]
+    fn waiters_notified_on_replacement() {
+        let mut timeline = Timeline::new();
+        let first = timeline.start("x", Tween::new(0., 10., linear(1.)));
+        let mut finished = timeline.finished(first);
+        timeline.start("x", Tween::new(0., 20., linear(1.)));
+        // Waiting for the replaced animation before the next step works as well
+        let mut replaced = timeline.finished(first);
+        assert_eq!(finished.try_recv(), Ok(None));
+        timeline.advance(0.1);
+        assert_eq!(finished.try_recv(), Ok(Some(())));
+        assert_eq!(replaced.try_recv(), Ok(Some(())));
+    }
+
+    #[test]
+    fn waiters_notified_without_applying_step() {
+        // The animator applies the values after the step is made and may fail doing so
+        let mut timeline = Timeline::new();
+        let id = timeline.start("x", Tween::new(0., 10., linear(1.)));
+        let mut finished = timeline.finished(id);
+        drop(timeline.advance(1.));
+        assert_eq!(finished.try_recv(), Ok(Some(())));
+    }
+
+    #[test]
+    fn waiting_for_stopped_animation_resolves_at_once() {
+        let mut timeline = Timeline::<&str, f32>::new();
+        assert_eq!(timeline.finished(42).now_or_never(), Some(Ok(())));
+        let id = timeline.start("x", Tween::new(0., 10., linear(1.)));
+        timeline.finish(id);
+        assert_eq!(timeline.finished(id).now_or_never(), Some(Ok(())));
+    }
+}
@@ -0,0 +1,177 @@
+use bindings::Windows::{
+    Foundation::Numerics::{Vector2, Vector3},
+    UI::{
+        Color,
+        Composition::{CompositionColorBrush, Visual},
+    },
+};
+
+use futures::channel::oneshot;
+
+use crate::animation::{AnimationId, Lerp, Step, Timeline, Transition, Tween};
+
+/// Animated property of a composition object
+#[derive(Clone, PartialEq)]
+pub enum AnimationTarget {
+    Offset(Visual),
+    Size(Visual),
+    /// Scale around the visual's center point
+    Scale(Visual),
+    Opacity(Visual),
+    Color(CompositionColorBrush),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AnimationValue {
+    Offset(Vector3),
+    Size(Vector2),
+    Scale(Vector3),
+    Opacity(f32),
+    Color(Color),
+}
+
+fn lerp_channel(from: u8, to: u8, t: f32) -> u8 {
+    f32::lerp(from as f32, to as f32, t).round().clamp(0., 255.) as u8
+}
+
+impl Lerp for AnimationValue {
+    fn lerp(from: Self, to: Self, t: f32) -> Self {
+        match (from, to) {
+            (AnimationValue::Offset(a), AnimationValue::Offset(b)) => {
+                AnimationValue::Offset(Vector3 {
+                    X: f32::lerp(a.X, b.X, t),
+                    Y: f32::lerp(a.Y, b.Y, t),
+                    Z: f32::lerp(a.Z, b.Z, t),
+                })
+            }
+            (AnimationValue::Size(a), AnimationValue::Size(b)) => AnimationValue::Size(Vector2 {
+                X: f32::lerp(a.X, b.X, t),
+                Y: f32::lerp(a.Y, b.Y, t),
+            }),
+            (AnimationValue::Scale(a), AnimationValue::Scale(b)) => {
+                AnimationValue::Scale(Vector3 {
+                    X: f32::lerp(a.X, b.X, t),
+                    Y: f32::lerp(a.Y, b.Y, t),
+                    Z: f32::lerp(a.Z, b.Z, t),
+                })
+            }
+            (AnimationValue::Opacity(a), AnimationValue::Opacity(b)) => {
+                AnimationValue::Opacity(f32::lerp(a, b, t))
+            }
+            (AnimationValue::Color(a), AnimationValue::Color(b)) => AnimationValue::Color(Color {
+                A: lerp_channel(a.A, b.A, t),
+                R: lerp_channel(a.R, b.R, t),
+                G: lerp_channel(a.G, b.G, t),
+                B: lerp_channel(a.B, b.B, t),
+            }),
+            // Values of different properties can't be mixed
+            (_, to) => to,
+        }
+    }
+}
+
+impl AnimationTarget {
+    fn value(&self) -> crate::Result<AnimationValue> {
+        Ok(match self {
+            AnimationTarget::Offset(visual) => AnimationValue::Offset(visual.Offset()?),
+            AnimationTarget::Size(visual) => AnimationValue::Size(visual.Size()?),
+            AnimationTarget::Scale(visual) => AnimationValue::Scale(visual.Scale()?),
+            AnimationTarget::Opacity(visual) => AnimationValue::Opacity(visual.Opacity()?),
+            AnimationTarget::Color(brush) => AnimationValue::Color(brush.Color()?),
+        })
+    }
+
+    fn apply(&self, value: AnimationValue) -> crate::Result<()> {
+        match (self, value) {
+            (AnimationTarget::Offset(visual), AnimationValue::Offset(offset)) => {
+                visual.SetOffset(offset)?
+            }
+            (AnimationTarget::Size(visual), AnimationValue::Size(size)) => visual.SetSize(size)?,
+            (AnimationTarget::Scale(visual), AnimationValue::Scale(scale)) => {
+                visual.SetScale(scale)?
+            }
+            (AnimationTarget::Opacity(visual), AnimationValue::Opacity(opacity)) => {
+                visual.SetOpacity(opacity)?
+            }
+            (AnimationTarget::Color(brush), AnimationValue::Color(color)) => {
+                brush.SetColor(color)?
+            }
+            _ => tracing::warn!(value = ?value, "animation value doesn't match its target"),
+        }
+        Ok(())
+    }
+}
+
+/// Animations of the frame. Values are applied to composition objects each time
+/// the animator is advanced, so the time is controlled by the application
+#[derive(Default)]
+pub struct Animator {
+    timeline: Timeline<AnimationTarget, AnimationValue>,
+}
+
+impl Animator {
+    /// Animates the property from its current value (or from the current value of the running
+    /// animation) to `to`. If the property is already animated to `to`, that animation continues
+    pub fn animate(
+        &mut self,
+        target: AnimationTarget,
+        to: AnimationValue,
+        transition: Transition,
+    ) -> crate::Result<AnimationId> {
+        if let Some((id, running_to)) = self.timeline.target(&target) {
+            if running_to == to {
+                return Ok(id);
+            }
+        }
+        let from = match self.timeline.value(&target) {
+            Some(value) => value,
+            None => target.value()?,
+        };
+        Ok(self
+            .timeline
+            .start(target, Tween::new(from, to, transition)))
+    }
+
+    /// Moves time forward by `dt` seconds and applies animated values.
+    /// Returns animations which are over
+    pub fn advance(&mut self, dt: f32) -> crate::Result<Vec<AnimationId>> {
+        let step = self.timeline.advance(dt);
+        self.apply(step)
+    }
+
+    /// Jumps to the end of the animation
+    pub fn finish(&mut self, id: AnimationId) -> crate::Result<Vec<AnimationId>> {
+        let step = self.timeline.finish(id);
+        self.apply(step)
+    }
+
+    /// Jumps to the end of all animations
+    pub fn finish_all(&mut self) -> crate::Result<Vec<AnimationId>> {
+        let step = self.timeline.finish_all();
+        self.apply(step)
+    }
+
+    /// Receiver notified when the animation is over. For animations which are
+    /// not running anymore it's notified at once
+    pub fn finished(&mut self, id: AnimationId) -> oneshot::Receiver<()> {
+        self.timeline.finished(id)
+    }
+
+    pub fn is_running(&self, id: AnimationId) -> bool {
+        self.timeline.is_running(id)
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.timeline.is_empty()
+    }
+
+    fn apply(
+        &mut self,
+        step: Step<AnimationTarget, AnimationValue>,
+    ) -> crate::Result<Vec<AnimationId>> {
+        for (target, value) in step.values {
+            target.apply(value)?;
+        }
+        Ok(step.finished)
+    }
+}
@@ -1,89 +1,403 @@
-use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+use std::sync::{Arc, RwLockReadGuard, RwLockWriteGuard};
 
 use async_object::{Keeper, Tag};
-use bindings::Windows::{
-    Foundation::Numerics::Vector2,
-    UI::{
-        Color,
-        Composition::{CompositionShape, ShapeVisual},
+use bindings::{
+    Microsoft::Graphics::Canvas::UI::Composition::CanvasComposition,
+    Windows::{
+        Foundation::{
+            Numerics::{Vector2, Vector3},
+            Size,
+        },
+        Graphics::DirectX::{DirectXAlphaMode, DirectXPixelFormat},
+        UI::{
+            Colors,
+            Composition::{
+                CompositionBrush, CompositionMappingMode, CompositionShadow, CompositionShape,
+                CompositionSurfaceBrush, ContainerVisual, DropShadow, ShapeVisual, SpriteVisual,
+            },
+        },
     },
 };
-use float_ord::FloatOrd;
-use futures::StreamExt;
+use futures::{Future, StreamExt};
 
-use crate::{slot::SlotPlug, FrameTag, ReceiveSlotEvent, SlotTag};
+use crate::{
+    image::bitmap_surface, layout::Thickness, slot::SlotPlug, Bitmap, FrameTag, ReceiveSlotEvent,
+    SlotTag, TaskGroup, Theme, ThemeColor,
+};
+
+/// Line along the edge of the background, drawn inside it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Border {
+    pub width: f32,
+    pub color: ThemeColor,
+}
+
+impl Border {
+    pub fn new(width: f32, color: impl Into<ThemeColor>) -> Self {
+        Self {
+            width,
+            color: color.into(),
+        }
+    }
+}
+
+/// Linear gradient drawn instead of the solid color. Points are relative to the size of
+/// the background: (0, 0) is the top left corner, (1, 1) is the bottom right one
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub start: Vector2,
+    pub end: Vector2,
+    /// Colors at offsets from 0 (start) to 1 (end)
+    pub stops: Vec<(f32, ThemeColor)>,
+}
+
+impl Gradient {
+    pub fn new(start: Vector2, end: Vector2) -> Self {
+        Self {
+            start,
+            end,
+            stops: Vec::new(),
+        }
+    }
+    pub fn vertical(top: impl Into<ThemeColor>, bottom: impl Into<ThemeColor>) -> Self {
+        Self::new(Vector2 { X: 0., Y: 0. }, Vector2 { X: 0., Y: 1. })
+            .stop(0., top)
+            .stop(1., bottom)
+    }
+    pub fn horizontal(left: impl Into<ThemeColor>, right: impl Into<ThemeColor>) -> Self {
+        Self::new(Vector2 { X: 0., Y: 0. }, Vector2 { X: 1., Y: 0. })
+            .stop(0., left)
+            .stop(1., right)
+    }
+    pub fn stop(mut self, offset: f32, color: impl Into<ThemeColor>) -> Self {
+        self.stops.push((offset, color.into()));
+        self
+    }
+}
+
+/// Image stretched over the background except its edges: `insets` are the sizes of
+/// the image's edges which keep their size. The image is drawn instead of the color
+#[derive(Clone, Debug, PartialEq)]
+pub struct NineGrid {
+    pub bitmap: Arc<Bitmap>,
+    pub insets: Thickness,
+}
+
+impl NineGrid {
+    pub fn new(bitmap: Arc<Bitmap>, insets: Thickness) -> Self {
+        Self { bitmap, insets }
+    }
+}
+
+/// Drop shadow under the background. It may go out of the slot, so it's cut
+/// by slots which clip to their bounds
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shadow {
+    pub color: ThemeColor,
+    pub offset: Vector2,
+    pub blur_radius: f32,
+    pub opacity: f32,
+}
+
+impl Shadow {
+    pub fn new(color: impl Into<ThemeColor>, offset: Vector2, blur_radius: f32) -> Self {
+        Self {
+            color: color.into(),
+            offset,
+            blur_radius,
+            opacity: 1.,
+        }
+    }
+}
+
+/// Rounded rectangle drawn for the shadow, redrawn only when its size or radius changes
+struct ShadowMask {
+    size: Vector2,
+    radius: f32,
+    brush: CompositionSurfaceBrush,
+}
+
+/// Rectangle of the shape with the border drawn inside the background
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct BorderInset {
+    offset: Vector2,
+    size: Vector2,
+    radius: f32,
+}
+
+/// The stroke is centered on the edge of the geometry, so the geometry is shrunk
+/// by half of the border on each side to keep the border inside
+fn border_inset(size: Vector2, border_width: f32, radius: f32) -> BorderInset {
+    BorderInset {
+        offset: Vector2 {
+            X: border_width / 2.,
+            Y: border_width / 2.,
+        },
+        size: Vector2 {
+            X: (size.X - border_width).max(0.),
+            Y: (size.Y - border_width).max(0.),
+        },
+        radius: (radius - border_width / 2.).max(0.),
+    }
+}
 
 pub struct Background {
     frame: FrameTag,
     slot: SlotPlug,
+    container: ContainerVisual,
+    /// Casts the shadow, lies below the image and the shape
+    shadow_visual: SpriteVisual,
+    drop_shadow: DropShadow,
+    shadow_mask: Option<ShadowMask>,
+    image_visual: SpriteVisual,
     shape: ShapeVisual,
     round_corners: bool,
-    color: Color,
+    color: ThemeColor,
+    border: Option<Border>,
+    gradient: Option<Gradient>,
+    nine_grid: Option<NineGrid>,
+    shadow: Option<Shadow>,
+    theme: Arc<Theme>,
+    tasks: TaskGroup,
 }
 
 impl Background {
     fn new(
         frame: FrameTag,
         slot: SlotTag,
-        color: Color,
+        color: ThemeColor,
         round_corners: bool,
     ) -> crate::Result<Self> {
         let compositor = frame.compositor()?;
+        let theme = frame.theme()?;
+        let container = compositor.CreateContainerVisual()?;
+        let shadow_visual = compositor.CreateSpriteVisual()?;
+        let drop_shadow = compositor.CreateDropShadow()?;
+        let image_visual = compositor.CreateSpriteVisual()?;
         let shape = compositor.CreateShapeVisual()?;
-        let slot = slot.plug(shape.clone().into())?;
-        let background = Self {
+        container.Children()?.InsertAtTop(shadow_visual.clone())?;
+        container.Children()?.InsertAtTop(image_visual.clone())?;
+        container.Children()?.InsertAtTop(shape.clone())?;
+        let slot = slot.plug("Background", container.clone().into())?;
+        let tasks = TaskGroup::for_slot(frame.clone(), &slot.tag())?;
+        let mut background = Self {
             frame,
             slot,
+            container,
+            shadow_visual,
+            drop_shadow,
+            shadow_mask: None,
+            image_visual,
             shape,
             color,
             round_corners,
+            border: None,
+            gradient: None,
+            nine_grid: None,
+            shadow: None,
+            theme,
+            tasks,
         };
         background.redraw()?;
         Ok(background)
     }
 
-    fn set_color(&mut self, color: Color) -> crate::Result<()> {
+    fn set_color(&mut self, color: ThemeColor) -> crate::Result<()> {
+        tracing::debug!(color = ?color, "background color");
         self.color = color;
         self.redraw()?;
         Ok(())
     }
 
+    fn set_border(&mut self, border: Option<Border>) -> crate::Result<()> {
+        self.border = border;
+        self.redraw()
+    }
+
+    fn set_gradient(&mut self, gradient: Option<Gradient>) -> crate::Result<()> {
+        self.gradient = gradient;
+        self.redraw()
+    }
+
+    fn set_nine_grid(&mut self, nine_grid: Option<NineGrid>) -> crate::Result<()> {
+        match &nine_grid {
+            Some(nine_grid) => {
+                let compositor = self.frame.compositor()?;
+                let surface = bitmap_surface(&self.frame, &nine_grid.bitmap)?;
+                let brush = compositor.CreateNineGridBrush()?;
+                brush.SetSource(compositor.CreateSurfaceBrushWithSurface(&surface)?)?;
+                let insets = nine_grid.insets;
+                brush.SetInsetsWithValues(insets.left, insets.top, insets.right, insets.bottom)?;
+                self.image_visual.SetBrush(brush)?;
+            }
+            None => self.image_visual.SetBrush(None::<CompositionBrush>)?,
+        }
+        self.nine_grid = nine_grid;
+        self.redraw()
+    }
+
+    fn set_shadow(&mut self, shadow: Option<Shadow>) -> crate::Result<()> {
+        self.shadow = shadow;
+        self.redraw()
+    }
+
+    fn set_theme(&mut self, theme: Arc<Theme>) -> crate::Result<()> {
+        self.theme = theme;
+        self.redraw()
+    }
+
     fn set_size(&mut self, size: Vector2) -> crate::Result<()> {
+        tracing::trace!(size = ?size, "background size");
+        self.container.SetSize(size)?;
+        self.shadow_visual.SetSize(size)?;
+        self.image_visual.SetSize(size)?;
         self.shape.SetSize(size)?;
         self.redraw()?;
         Ok(())
     }
 
-    fn redraw(&self) -> crate::Result<()> {
+    fn redraw(&mut self) -> crate::Result<()> {
         self.shape.Shapes()?.Clear()?;
         self.shape
             .Shapes()?
             .Append(self.create_background_shape()?)?;
-        Ok(())
+        self.redraw_shadow()
+    }
+
+    fn corner_radius(&self, size: Vector2) -> f32 {
+        if self.round_corners {
+            self.theme.corner_radius(size)
+        } else {
+            0.
+        }
     }
+
     fn create_background_shape(&self) -> crate::Result<CompositionShape> {
         let compositor = self.frame.compositor()?;
         let container_shape = compositor.CreateContainerShape()?;
+        let size = self.shape.Size()?;
+        let border_width = self.border.as_ref().map_or(0., |border| border.width);
+        let inset = border_inset(size, border_width, self.corner_radius(size));
         let rect_geometry = compositor.CreateRoundedRectangleGeometry()?;
-        rect_geometry.SetSize(self.shape.Size()?)?;
-        if self.round_corners {
-            let size = rect_geometry.Size()?;
-            let radius = std::cmp::min(FloatOrd(size.X), FloatOrd(size.Y)).0 / 20.;
-            rect_geometry.SetCornerRadius(Vector2 {
-                X: radius,
-                Y: radius,
-            })?;
-        } else {
-            rect_geometry.SetCornerRadius(Vector2 { X: 0., Y: 0. })?;
-        }
-        let brush = compositor.CreateColorBrushWithColor(self.color.clone())?;
+        rect_geometry.SetSize(inset.size)?;
+        rect_geometry.SetCornerRadius(Vector2 {
+            X: inset.radius,
+            Y: inset.radius,
+        })?;
         let rect = compositor.CreateSpriteShapeWithGeometry(rect_geometry)?;
-        rect.SetFillBrush(brush)?;
-        rect.SetOffset(Vector2 { X: 0., Y: 0. })?;
+        // The nine-grid image is seen through the shape
+        if self.nine_grid.is_none() {
+            rect.SetFillBrush(self.create_fill_brush()?)?;
+        }
+        if let Some(border) = &self.border {
+            let brush = compositor.CreateColorBrushWithColor(self.theme.resolve(&border.color))?;
+            rect.SetStrokeBrush(brush)?;
+            rect.SetStrokeThickness(border.width)?;
+        }
+        rect.SetOffset(inset.offset)?;
         container_shape.Shapes()?.Append(rect)?;
         let shape = container_shape.into();
         Ok(shape)
     }
+
+    fn create_fill_brush(&self) -> crate::Result<CompositionBrush> {
+        let compositor = self.frame.compositor()?;
+        let gradient = match &self.gradient {
+            Some(gradient) => gradient,
+            None => {
+                let brush =
+                    compositor.CreateColorBrushWithColor(self.theme.resolve(&self.color))?;
+                return Ok(brush.into());
+            }
+        };
+        let brush = compositor.CreateLinearGradientBrush()?;
+        brush.SetMappingMode(CompositionMappingMode::Relative)?;
+        brush.SetStartPoint(gradient.start)?;
+        brush.SetEndPoint(gradient.end)?;
+        let stops = brush.ColorStops()?;
+        for (offset, color) in &gradient.stops {
+            stops.Append(
+                compositor.CreateColorGradientStopWithOffsetAndColor(
+                    *offset,
+                    self.theme.resolve(color),
+                )?,
+            )?;
+        }
+        Ok(brush.into())
+    }
+
+    fn redraw_shadow(&mut self) -> crate::Result<()> {
+        let shadow = match &self.shadow {
+            Some(shadow) => shadow,
+            None => {
+                self.shadow_visual.SetShadow(None::<CompositionShadow>)?;
+                return Ok(());
+            }
+        };
+        self.drop_shadow
+            .SetColor(self.theme.resolve(&shadow.color))?;
+        self.drop_shadow.SetOffset(Vector3 {
+            X: shadow.offset.X,
+            Y: shadow.offset.Y,
+            Z: 0.,
+        })?;
+        self.drop_shadow.SetBlurRadius(shadow.blur_radius)?;
+        self.drop_shadow.SetOpacity(shadow.opacity)?;
+        // Without a mask the shadow is of the visual's rectangle
+        let size = self.shadow_visual.Size()?;
+        let radius = self.corner_radius(size);
+        if radius > 0. {
+            let mask = self.shadow_mask(size, radius)?;
+            self.drop_shadow.SetMask(mask)?;
+        } else {
+            self.drop_shadow.SetMask(None::<CompositionBrush>)?;
+        }
+        self.shadow_visual.SetShadow(self.drop_shadow.clone())?;
+        Ok(())
+    }
+
+    /// Rounded rectangle which gives its shape to the shadow
+    fn shadow_mask(
+        &mut self,
+        size: Vector2,
+        radius: f32,
+    ) -> crate::Result<CompositionSurfaceBrush> {
+        if let Some(mask) = &self.shadow_mask {
+            if mask.size == size && mask.radius == radius {
+                return Ok(mask.brush.clone());
+            }
+        }
+        let surface = self.frame.graphics_device()?.CreateDrawingSurface(
+            Size {
+                Width: size.X.max(1.),
+                Height: size.Y.max(1.),
+            },
+            DirectXPixelFormat::B8G8R8A8UIntNormalized,
+            DirectXAlphaMode::Premultiplied,
+        )?;
+        let session = CanvasComposition::CreateDrawingSession(&surface)?;
+        session.Clear(Colors::Transparent()?)?;
+        session.FillRoundedRectangleAtCoordsWithColor(
+            0.,
+            0.,
+            size.X,
+            size.Y,
+            radius,
+            radius,
+            Colors::Black()?,
+        )?;
+        session.Close()?;
+        let brush = self
+            .frame
+            .compositor()?
+            .CreateSurfaceBrushWithSurface(&surface)?;
+        self.shadow_mask = Some(ShadowMask {
+            size,
+            radius,
+            brush: brush.clone(),
+        });
+        Ok(brush)
+    }
 }
 
 #[derive(Clone)]
@@ -93,10 +407,10 @@ impl BackgroundKeeper {
     pub fn new(
         frame: FrameTag,
         slot: SlotTag,
-        color: Color,
+        color: impl Into<ThemeColor>,
         round_corners: bool,
     ) -> crate::Result<Self> {
-        let keeper = Keeper::new(Background::new(frame, slot, color, round_corners)?);
+        let keeper = Keeper::new(Background::new(frame, slot, color.into(), round_corners)?);
         let keeper = Self(keeper);
         keeper.spawn_event_handlers()?;
         Ok(keeper)
@@ -110,16 +424,33 @@ impl BackgroundKeeper {
     pub fn get_mut(&self) -> RwLockWriteGuard<'_, Background> {
         self.0.get_mut()
     }
+    /// Resolves when event handlers of the background are stopped, i.e. after all keepers are dropped
+    pub fn tasks_finished(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.get().tasks.finished()
+    }
     fn spawn_event_handlers(&self) -> crate::Result<()> {
         let tag = self.tag();
-        let frame = self.get().frame.clone();
         let slot = self.get().slot.tag();
-        frame.thread_spawn(async move {
-            while let Some(size) = slot.on_size().next().await {
-                tag.set_size(size.0)?;
-            }
-            Ok(())
-        })
+        {
+            let tag = tag.clone();
+            let slot = slot.clone();
+            self.get_mut()
+                .tasks
+                .spawn("Background::on_size", async move {
+                    while let Some(size) = slot.on_size().next().await {
+                        tag.set_size(size.0)?;
+                    }
+                    Ok(())
+                })?;
+        }
+        self.get_mut()
+            .tasks
+            .spawn("Background::on_theme_changed", async move {
+                while let Some(event) = slot.on_theme_changed().next().await {
+                    tag.set_theme(event.0)?;
+                }
+                Ok(())
+            })
     }
 }
 #[derive(Clone, PartialEq)]
@@ -129,13 +460,85 @@ impl BackgroundTag {
     pub fn round_corners(&self) -> crate::Result<bool> {
         Ok(self.0.call(|v| v.round_corners)?)
     }
-    pub fn color(&self) -> crate::Result<Color> {
-        Ok(self.0.call(|v| v.color)?)
+    pub fn color(&self) -> crate::Result<ThemeColor> {
+        Ok(self.0.call(|v| v.color.clone())?)
     }
-    pub fn set_color(&self, color: Color) -> crate::Result<()> {
+    pub fn set_color(&self, color: impl Into<ThemeColor>) -> crate::Result<()> {
+        let color = color.into();
         Ok(self.0.call_mut(|v| v.set_color(color))??)
     }
+    pub fn border(&self) -> crate::Result<Option<Border>> {
+        Ok(self.0.call(|v| v.border.clone())?)
+    }
+    pub fn set_border(&self, border: Option<Border>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_border(border))??)
+    }
+    pub fn gradient(&self) -> crate::Result<Option<Gradient>> {
+        Ok(self.0.call(|v| v.gradient.clone())?)
+    }
+    /// Draws the gradient instead of the color
+    pub fn set_gradient(&self, gradient: Option<Gradient>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_gradient(gradient))??)
+    }
+    pub fn nine_grid(&self) -> crate::Result<Option<NineGrid>> {
+        Ok(self.0.call(|v| v.nine_grid.clone())?)
+    }
+    /// Draws the image instead of the color or gradient
+    pub fn set_nine_grid(&self, nine_grid: Option<NineGrid>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_nine_grid(nine_grid))??)
+    }
+    pub fn shadow(&self) -> crate::Result<Option<Shadow>> {
+        Ok(self.0.call(|v| v.shadow.clone())?)
+    }
+    pub fn set_shadow(&self, shadow: Option<Shadow>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_shadow(shadow))??)
+    }
+    fn set_theme(&self, theme: Arc<Theme>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_theme(theme))??)
+    }
     pub fn set_size(&self, size: Vector2) -> crate::Result<()> {
         Ok(self.0.call_mut(|v| v.set_size(size))??)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bindings::Windows::Foundation::Numerics::Vector2;
+
+    use super::{border_inset, BorderInset};
+
+    fn vector(x: f32, y: f32) -> Vector2 {
+        Vector2 { X: x, Y: y }
+    }
+
+    #[test]
+    fn border_is_drawn_inside() {
+        assert_eq!(
+            border_inset(vector(100., 50.), 4., 10.),
+            BorderInset {
+                offset: vector(2., 2.),
+                size: vector(96., 46.),
+                radius: 8.,
+            }
+        );
+    }
+
+    #[test]
+    fn no_border_keeps_rectangle() {
+        assert_eq!(
+            border_inset(vector(100., 50.), 0., 10.),
+            BorderInset {
+                offset: vector(0., 0.),
+                size: vector(100., 50.),
+                radius: 10.,
+            }
+        );
+    }
+
+    #[test]
+    fn border_wider_than_background_leaves_nothing_inside() {
+        let inset = border_inset(vector(6., 20.), 8., 2.);
+        assert_eq!(inset.size, vector(0., 12.));
+        assert_eq!(inset.radius, 0.);
+    }
+}
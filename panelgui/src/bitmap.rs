@@ -0,0 +1,119 @@
+//! Raster images decoded in pure Rust, independent of the composition backend
+
+use std::path::Path;
+
+use image::RgbaImage;
+
+/// Decoded image: RGBA pixels, 8 bits per channel, premultiplied by alpha, row by row
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bitmap {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Bitmap {
+    /// Bitmap from RGBA pixels with straight (not premultiplied) alpha.
+    /// Returns `None` if the number of pixels doesn't match the size
+    pub fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Option<Self> {
+        // `RgbaImage::from_raw` accepts extra data at the end
+        if pixels.len() as u64 != width as u64 * height as u64 * 4 {
+            return None;
+        }
+        RgbaImage::from_raw(width, height, pixels).map(Self::from_image)
+    }
+
+    /// Decodes PNG or JPEG data
+    pub fn decode(data: &[u8]) -> crate::Result<Self> {
+        Ok(Self::from_image(image::load_from_memory(data)?.to_rgba8()))
+    }
+
+    /// Loads PNG or JPEG file, the format is detected by the file's extension
+    pub fn load(path: impl AsRef<Path>) -> crate::Result<Self> {
+        Ok(Self::from_image(image::open(path)?.to_rgba8()))
+    }
+
+    fn from_image(image: RgbaImage) -> Self {
+        let (width, height) = image.dimensions();
+        let mut pixels = image.into_raw();
+        for pixel in pixels.chunks_exact_mut(4) {
+            let alpha = pixel[3] as u32;
+            for channel in &mut pixel[..3] {
+                *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bitmap;
+
+    /// 2x1 PNG: opaque red and half transparent blue
+    const PNG: [u8; 74] = [
+        137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 2, 0, 0, 0, 1, 8, 6,
+        0, 0, 0, 244, 34, 127, 138, 0, 0, 0, 17, 73, 68, 65, 84, 120, 156, 99, 252, 207, 192, 240,
+        159, 145, 225, 127, 35, 0, 15, 136, 3, 129, 34, 73, 174, 222, 0, 0, 0, 0, 73, 69, 78, 68,
+        174, 66, 96, 130,
+    ];
+
+    #[test]
+    fn from_rgba_checks_size() {
+        assert_eq!(Bitmap::from_rgba(2, 2, vec![0; 12]), None);
+        assert_eq!(
+            Bitmap::from_rgba(1, 1, vec![0; 8]).map(|b| b.pixels().len()),
+            None
+        );
+        let bitmap = Bitmap::from_rgba(2, 1, vec![0; 8]).unwrap();
+        assert_eq!((bitmap.width(), bitmap.height()), (2, 1));
+        assert!(Bitmap::from_rgba(0, 3, Vec::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn pixels_are_premultiplied() {
+        let bitmap = Bitmap::from_rgba(
+            4,
+            1,
+            vec![
+                255, 128, 0, 128, // rounded to nearest
+                255, 255, 255, 255, // opaque stays
+                200, 100, 50, 0, // transparent becomes black
+                1, 2, 3, 127,
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            bitmap.pixels(),
+            &[128, 64, 0, 128, 255, 255, 255, 255, 0, 0, 0, 0, 0, 1, 1, 127]
+        );
+    }
+
+    #[test]
+    fn decodes_png() {
+        let bitmap = Bitmap::decode(&PNG).unwrap();
+        assert_eq!((bitmap.width(), bitmap.height()), (2, 1));
+        assert_eq!(bitmap.pixels(), &[255, 0, 0, 255, 0, 0, 128, 128]);
+        assert!(Bitmap::decode(&PNG[..20]).is_err());
+    }
+}
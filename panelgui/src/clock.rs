@@ -0,0 +1,230 @@
+use std::time::{Duration, Instant};
+
+use futures::channel::{
+    mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
+
+/// Frame time after the clock was advanced. Times are in seconds since the clock was created
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tick {
+    pub time: f64,
+    /// Time passed since the previous tick
+    pub dt: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ClockMode {
+    /// Time follows the system clock: each `Clock::tick` advances it by the real time passed
+    Real,
+    /// Time moves only by `Clock::advance`, so time-based behavior is deterministic in tests
+    Virtual,
+}
+
+enum TimerSender {
+    Once(oneshot::Sender<()>),
+    Interval {
+        sender: UnboundedSender<Tick>,
+        period: f64,
+    },
+}
+
+struct Timer {
+    deadline: f64,
+    sender: TimerSender,
+}
+
+impl Timer {
+    fn is_closed(&self) -> bool {
+        match &self.sender {
+            TimerSender::Once(sender) => sender.is_canceled(),
+            TimerSender::Interval { sender, .. } => sender.is_closed(),
+        }
+    }
+}
+
+/// Time of the frame. Nothing happens by itself: the clock is driven by the window's
+/// timer in `Real` mode or by the test in `Virtual` mode
+pub struct Clock {
+    mode: ClockMode,
+    now: f64,
+    last_tick: Option<Instant>,
+    timers: Vec<Timer>,
+    tick_senders: Vec<UnboundedSender<Tick>>,
+}
+
+impl Clock {
+    pub fn new(mode: ClockMode) -> Self {
+        Self {
+            mode,
+            now: 0.,
+            last_tick: None,
+            timers: Vec::new(),
+            tick_senders: Vec::new(),
+        }
+    }
+
+    pub fn mode(&self) -> ClockMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: ClockMode) {
+        self.mode = mode;
+        self.last_tick = None;
+    }
+
+    pub fn now(&self) -> f64 {
+        self.now
+    }
+
+    /// Real time passed since the previous call, or `None` for the virtual clock.
+    /// The first call after creation or mode change returns zero
+    pub fn tick(&mut self) -> Option<f32> {
+        if self.mode == ClockMode::Virtual {
+            return None;
+        }
+        let now = Instant::now();
+        let dt = self.last_tick.map_or(0., |last| (now - last).as_secs_f32());
+        self.last_tick = Some(now);
+        Some(dt)
+    }
+
+    /// Moves time forward by `dt` seconds: fires expired timers and sends `Tick` to
+    /// subscribers. An interval fires at most once per advance, skipping missed periods
+    pub fn advance(&mut self, dt: f32) {
+        self.now += dt.max(0.) as f64;
+        let tick = Tick { time: self.now, dt };
+        for mut timer in std::mem::take(&mut self.timers) {
+            if timer.deadline > self.now {
+                if !timer.is_closed() {
+                    self.timers.push(timer);
+                }
+                continue;
+            }
+            match timer.sender {
+                TimerSender::Once(sender) => {
+                    let _ = sender.send(());
+                }
+                TimerSender::Interval { ref sender, period } => {
+                    if sender.unbounded_send(tick).is_ok() {
+                        let missed = ((self.now - timer.deadline) / period).floor() + 1.;
+                        timer.deadline += missed * period;
+                        self.timers.push(timer);
+                    }
+                }
+            }
+        }
+        self.tick_senders
+            .retain(|sender| sender.unbounded_send(tick).is_ok());
+    }
+
+    /// Receiver of `Tick` for each advance of the clock
+    pub fn on_tick(&mut self) -> UnboundedReceiver<Tick> {
+        let (sender, receiver) = unbounded();
+        self.tick_senders.push(sender);
+        receiver
+    }
+
+    /// Receiver notified when `delay` passes
+    pub fn timer(&mut self, delay: Duration) -> oneshot::Receiver<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.timers.push(Timer {
+            deadline: self.now + delay.as_secs_f64(),
+            sender: TimerSender::Once(sender),
+        });
+        receiver
+    }
+
+    /// Receiver of `Tick` each `period`, the first one after `period` passes.
+    /// Stops when the receiver is dropped
+    pub fn interval(&mut self, period: Duration) -> UnboundedReceiver<Tick> {
+        let (sender, receiver) = unbounded();
+        // Zero period would fire on every advance
+        let period = period.as_secs_f64().max(f64::EPSILON);
+        self.timers.push(Timer {
+            deadline: self.now + period,
+            sender: TimerSender::Interval { sender, period },
+        });
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::{FutureExt, StreamExt};
+
+    use super::{Clock, ClockMode, Tick};
+
+    #[test]
+    fn timer_fires_at_deadline() {
+        let mut clock = Clock::new(ClockMode::Virtual);
+        let mut timer = clock.timer(Duration::from_millis(500));
+        clock.advance(0.25);
+        assert_eq!(timer.try_recv(), Ok(None));
+        clock.advance(0.25);
+        assert_eq!(timer.try_recv(), Ok(Some(())));
+        assert!(clock.timers.is_empty());
+    }
+
+    #[test]
+    fn interval_skips_missed_periods() {
+        let mut clock = Clock::new(ClockMode::Virtual);
+        let mut ticks = clock.interval(Duration::from_millis(500));
+        clock.advance(0.25);
+        assert_eq!(ticks.next().now_or_never(), None);
+        clock.advance(0.25);
+        assert_eq!(
+            ticks.next().now_or_never(),
+            Some(Some(Tick {
+                time: 0.5,
+                dt: 0.25
+            }))
+        );
+        // Three periods pass at once: one tick, the next deadline is at 2.5
+        clock.advance(1.75);
+        assert_eq!(
+            ticks.next().now_or_never(),
+            Some(Some(Tick {
+                time: 2.25,
+                dt: 1.75
+            }))
+        );
+        assert_eq!(ticks.next().now_or_never(), None);
+        assert_eq!(clock.timers[0].deadline, 2.5);
+        clock.advance(0.25);
+        assert_eq!(
+            ticks.next().now_or_never(),
+            Some(Some(Tick {
+                time: 2.5,
+                dt: 0.25
+            }))
+        );
+    }
+
+    #[test]
+    fn dropped_receivers_are_pruned() {
+        let mut clock = Clock::new(ClockMode::Virtual);
+        let timer = clock.timer(Duration::from_secs(1));
+        let ticks = clock.interval(Duration::from_secs(1));
+        let on_tick = clock.on_tick();
+        let _kept = clock.interval(Duration::from_secs(1));
+        drop((timer, ticks, on_tick));
+        // Pending timers are pruned on advance, the expired interval when it fails to send
+        clock.advance(0.5);
+        assert_eq!(clock.timers.len(), 1);
+        assert!(clock.tick_senders.is_empty());
+    }
+
+    #[test]
+    fn virtual_clock_does_not_tick() {
+        let mut clock = Clock::new(ClockMode::Virtual);
+        assert_eq!(clock.tick(), None);
+        clock.set_mode(ClockMode::Real);
+        assert_eq!(clock.tick(), Some(0.));
+        clock.set_mode(ClockMode::Virtual);
+        assert_eq!(clock.tick(), None);
+        assert_eq!(clock.now(), 0.);
+    }
+}
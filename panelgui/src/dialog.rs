@@ -0,0 +1,216 @@
+use std::sync::{Arc, Mutex};
+
+use futures::{channel::oneshot, StreamExt};
+
+use crate::{
+    layout::{Alignment, Thickness},
+    tree::{BackgroundDesc, BorderDesc, CellDesc, RibbonDesc, ShadowDesc, TextDesc, Tree},
+    BackgroundKeeper, CellLimit, FrameTag, Key, ReceiveSlotEvent, RibbonKeeper, RibbonOrientation,
+    SlotTag, TaskGroup,
+};
+
+/// Closes the dialog. Clones can be moved into event handlers of the dialog's content
+pub struct DialogHandle<T> {
+    sender: Arc<Mutex<Option<oneshot::Sender<Option<T>>>>>,
+}
+
+impl<T> Clone for DialogHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<T> DialogHandle<T> {
+    /// Closes the dialog with the result. Returns false if the dialog is already closed
+    pub fn close(&self, result: T) -> bool {
+        self.send(Some(result))
+    }
+
+    /// Closes the dialog without a result, as Escape does
+    pub fn cancel(&self) -> bool {
+        self.send(None)
+    }
+
+    fn send(&self, result: Option<T>) -> bool {
+        match self.sender.lock().unwrap().take() {
+            Some(sender) => sender.send(result).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// Modal layer of the frame: a backdrop dimming everything below and the content above it.
+/// Slots below get no input while the dialog is open, focus is trapped in the dialog.
+/// Escape cancels the dialog. Dropping the dialog closes it
+pub struct Dialog<T> {
+    frame: FrameTag,
+    layer: SlotTag,
+    content: SlotTag,
+    handle: DialogHandle<T>,
+    result: oneshot::Receiver<Option<T>>,
+    tree: Option<Tree>,
+    _ribbon: RibbonKeeper,
+    _backdrop: BackgroundKeeper,
+    tasks: TaskGroup,
+}
+
+impl<T: Send + 'static> Dialog<T> {
+    /// Opens an empty dialog. The content slot is placed in the middle of the frame
+    /// by `limit`, e.g. `CellLimit::auto()` sizes it by the content's desired size.
+    /// Focus is trapped in the dialog at once, Tab reaches the content built later
+    pub fn open(frame: FrameTag, limit: CellLimit) -> crate::Result<Self> {
+        let layer = frame.open_modal_slot()?;
+        frame.trap_focus(layer.clone())?;
+        let backdrop = BackgroundKeeper::new(frame.clone(), layer.clone(), "backdrop", false)?;
+        let ribbon = RibbonKeeper::new(frame.clone(), layer.clone(), RibbonOrientation::Stack)?;
+        let content = ribbon.tag().add_cell(limit)?;
+        let (sender, result) = oneshot::channel();
+        let handle = DialogHandle {
+            sender: Arc::new(Mutex::new(Some(sender))),
+        };
+        let mut tasks = TaskGroup::new(frame.clone());
+        {
+            let layer = layer.clone();
+            let handle = handle.clone();
+            tasks.spawn("Dialog::on_key_pressed", async move {
+                let mut keys = layer.on_key_pressed();
+                while let Some(event) = keys.next().await {
+                    if event.key == Key::Escape {
+                        handle.cancel();
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        tracing::debug!(layer = layer.id()?, "dialog opened");
+        Ok(Self {
+            frame,
+            layer,
+            content,
+            handle,
+            result,
+            tree: None,
+            _ribbon: ribbon,
+            _backdrop: backdrop,
+            tasks,
+        })
+    }
+
+    /// Slot for the content of the dialog
+    pub fn content(&self) -> SlotTag {
+        self.content.clone()
+    }
+
+    pub fn handle(&self) -> DialogHandle<T> {
+        self.handle.clone()
+    }
+
+    /// Makes the slot a button closing the dialog with `result` when it's clicked,
+    /// or when Enter or Space is pressed while it's focused
+    pub fn close_on_click(&mut self, slot: SlotTag, result: T) -> crate::Result<()>
+    where
+        T: Clone,
+    {
+        {
+            let slot = slot.clone();
+            let handle = self.handle.clone();
+            let result = result.clone();
+            self.tasks.spawn("Dialog::on_button_clicked", async move {
+                let mut clicks = slot.on_mouse_left_pressed();
+                while let Some(event) = clicks.next().await {
+                    if slot.contains(event.0)? {
+                        handle.close(result.clone());
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        let handle = self.handle.clone();
+        self.tasks.spawn("Dialog::on_button_key", async move {
+            let mut keys = slot.on_key_pressed();
+            while let Some(event) = keys.next().await {
+                if (event.key == Key::Enter || event.key == Key::Space) && slot.is_focused()? {
+                    handle.close(result.clone());
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Waits until the dialog is closed. The result is `None` if it was cancelled
+    pub async fn result(mut self) -> crate::Result<Option<T>> {
+        // Content is usually built after the dialog is opened, so it's arranged only now
+        self.frame.request_layout()?;
+        let result = (&mut self.result).await.unwrap_or(None);
+        tracing::debug!(
+            layer = self.layer.id()?,
+            cancelled = result.is_none(),
+            "dialog closed"
+        );
+        Ok(result)
+    }
+}
+
+impl<T: Clone + Send + 'static> Dialog<T> {
+    /// Dialog with a message and a row of buttons, each closing it with its result
+    pub fn message(
+        frame: FrameTag,
+        message: &str,
+        buttons: Vec<(String, T)>,
+    ) -> crate::Result<Self> {
+        let mut limit = CellLimit::auto();
+        limit.set_align(Alignment::Center);
+        let mut dialog = Self::open(frame.clone(), limit)?;
+        let mut row = RibbonDesc::new(RibbonOrientation::Horizontal).spacing(10.);
+        for (index, (label, _)) in buttons.iter().enumerate() {
+            row = row.cell(
+                CellDesc::auto()
+                    .named(format!("button{}", index))
+                    .focusable(true)
+                    .with(BackgroundDesc::new("secondary").round_corners(true))
+                    .with(
+                        RibbonDesc::new(RibbonOrientation::Stack)
+                            .padding(Thickness::uniform(10.))
+                            .cell(
+                                CellDesc::auto()
+                                    .with(TextDesc::new(label.as_str(), 16., "text").font("text")),
+                            ),
+                    ),
+            );
+        }
+        let panel = [
+            BackgroundDesc::new("primary")
+                .round_corners(true)
+                .border(BorderDesc::new(2., "accent"))
+                .shadow(ShadowDesc::new("shadow", (0., 4.), 16.))
+                .into(),
+            RibbonDesc::new(RibbonOrientation::Vertical)
+                .padding(Thickness::uniform(20.))
+                .spacing(20.)
+                .cell(CellDesc::auto().with(TextDesc::new(message, 32., "text").font("title")))
+                .cell(CellDesc::auto().with(row))
+                .into(),
+        ];
+        let tree = Tree::build(&frame, &dialog.content, &panel)?;
+        for (index, (_, result)) in buttons.into_iter().enumerate() {
+            if let Some(slot) = tree.slot(&format!("button{}", index)) {
+                dialog.close_on_click(slot, result)?;
+            }
+        }
+        if let Some(first) = tree.slot("button0") {
+            frame.set_focus(Some(first))?;
+        }
+        dialog.tree = Some(tree);
+        Ok(dialog)
+    }
+}
+
+impl<T> Drop for Dialog<T> {
+    fn drop(&mut self) {
+        // Focus returns to where it was before the dialog
+        let _ = self.frame.release_focus(self.layer.clone());
+        let _ = self.frame.close_slot(self.layer.clone());
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
+    task::Context,
+};
+
+use futures::{
+    executor::ThreadPool,
+    future::BoxFuture,
+    task::{waker_ref, ArcWake},
+    Future, FutureExt,
+};
+
+type TaskQueue = Mutex<VecDeque<Arc<ManualTask>>>;
+
+struct ManualTask {
+    future: Mutex<Option<BoxFuture<'static, ()>>>,
+    queue: Weak<TaskQueue>,
+    queued: AtomicBool,
+}
+
+impl ArcWake for ManualTask {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        if !arc_self.queued.swap(true, Ordering::SeqCst) {
+            if let Some(queue) = arc_self.queue.upgrade() {
+                queue.lock().unwrap().push_back(arc_self.clone());
+            }
+        }
+    }
+}
+
+/// Executor which runs tasks only when explicitly driven by `run_until_stalled`,
+/// in the order they were spawned or woken up. Unlike `futures::executor::LocalPool`
+/// it can be shared between threads, so it can be stored in the frame and used from tags.
+#[derive(Clone, Default)]
+pub struct ManualExecutor {
+    queue: Arc<TaskQueue>,
+}
+
+impl ManualExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn_ok<Fut>(&self, future: Fut)
+    where
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let task = Arc::new(ManualTask {
+            future: Mutex::new(Some(future.boxed())),
+            queue: Arc::downgrade(&self.queue),
+            queued: AtomicBool::new(true),
+        });
+        self.queue.lock().unwrap().push_back(task);
+    }
+
+    /// Polls tasks until none of them is ready to make progress.
+    /// Returns false if there was nothing to run
+    pub fn run_until_stalled(&self) -> bool {
+        let mut progress = false;
+        loop {
+            let task = match self.queue.lock().unwrap().pop_front() {
+                Some(task) => task,
+                None => break,
+            };
+            progress = true;
+            task.queued.store(false, Ordering::SeqCst);
+            let waker = waker_ref(&task);
+            let mut cx = Context::from_waker(&waker);
+            let mut future_slot = task.future.lock().unwrap();
+            if let Some(mut future) = future_slot.take() {
+                if future.as_mut().poll(&mut cx).is_pending() {
+                    *future_slot = Some(future);
+                }
+            }
+        }
+        progress
+    }
+}
+
+/// Executor for the frame's tasks: either a thread pool, or a `ManualExecutor`
+/// which makes the order of event handling deterministic
+#[derive(Clone)]
+pub enum Executor {
+    ThreadPool(ThreadPool),
+    Manual(ManualExecutor),
+}
+
+impl Executor {
+    pub fn spawn_ok<Fut>(&self, future: Fut)
+    where
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        match self {
+            Executor::ThreadPool(thread_pool) => thread_pool.spawn_ok(future),
+            Executor::Manual(executor) => executor.spawn_ok(future),
+        }
+    }
+
+    /// Runs pending tasks of the manual executor. Does nothing for the thread pool
+    pub fn run_until_stalled(&self) -> bool {
+        match self {
+            Executor::ThreadPool(_) => false,
+            Executor::Manual(executor) => executor.run_until_stalled(),
+        }
+    }
+}
+
+impl From<ThreadPool> for Executor {
+    fn from(thread_pool: ThreadPool) -> Self {
+        Executor::ThreadPool(thread_pool)
+    }
+}
+
+impl From<ManualExecutor> for Executor {
+    fn from(executor: ManualExecutor) -> Self {
+        Executor::Manual(executor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures::channel::oneshot;
+
+    use super::ManualExecutor;
+
+    #[test]
+    fn tasks_run_in_spawn_order() {
+        let executor = ManualExecutor::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        for index in 0..5 {
+            let log = log.clone();
+            executor.spawn_ok(async move { log.lock().unwrap().push(index) });
+        }
+        assert!(log.lock().unwrap().is_empty());
+        assert!(executor.run_until_stalled());
+        assert_eq!(*log.lock().unwrap(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn woken_task_runs_in_same_call() {
+        let executor = ManualExecutor::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (sender, receiver) = oneshot::channel();
+        {
+            let log = log.clone();
+            executor.spawn_ok(async move {
+                log.lock().unwrap().push("waiting");
+                let message = receiver.await.unwrap();
+                log.lock().unwrap().push(message);
+            });
+        }
+        {
+            let log = log.clone();
+            let executor_clone = executor.clone();
+            executor.spawn_ok(async move {
+                log.lock().unwrap().push("sending");
+                sender.send("received").unwrap();
+                // Spawned from a task, it's queued after the woken one
+                let log = log.clone();
+                executor_clone.spawn_ok(async move { log.lock().unwrap().push("spawned") });
+            });
+        }
+        assert!(executor.run_until_stalled());
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["waiting", "sending", "received", "spawned"]
+        );
+        assert!(!executor.run_until_stalled());
+    }
+
+    #[test]
+    fn returns_when_queue_is_empty() {
+        let executor = ManualExecutor::new();
+        assert!(!executor.run_until_stalled());
+        let (sender, receiver) = oneshot::channel::<()>();
+        executor.spawn_ok(async move {
+            let _ = receiver.await;
+        });
+        assert!(executor.run_until_stalled());
+        // The task waits for the sender, so there is nothing to run
+        assert!(!executor.run_until_stalled());
+        drop(sender);
+        assert!(executor.run_until_stalled());
+        assert!(!executor.run_until_stalled());
+    }
+}
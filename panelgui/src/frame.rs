@@ -1,37 +1,111 @@
-use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+    time::Duration,
+};
 
 use async_object::{Keeper, Tag};
-use bindings::Windows::UI::Composition::{Compositor, ContainerVisual};
-use futures::{executor::ThreadPool, Future};
+use bindings::{
+    Microsoft::Graphics::Canvas::{CanvasDevice, UI::Composition::CanvasComposition},
+    Windows::{
+        Foundation::Numerics::Vector2,
+        UI::Composition::{CompositionGraphicsDevice, Compositor, ContainerVisual},
+    },
+};
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    future::join_all,
+    Future, FutureExt,
+};
 
 use crate::{
-    slot::{SlotKeeper, SlotTag},
-    slot_event::{MouseLeftPressed, MouseLeftPressedFocused, SendSlotEvent},
-    SlotSize,
+    animation::{AnimationId, Transition},
+    clock::{Clock, ClockMode, Tick},
+    executor::Executor,
+    inspector::{FrameInfo, SlotInfo},
+    slot::{Hit, SlotKeeper, SlotTag},
+    slot_event::{
+        FocusChanged, Key, KeyPressed, MouseLeftPressed, MouseLeftPressedFocused, SendSlotEvent,
+        ThemeChanged,
+    },
+    AnimationTarget, AnimationValue, Animator, SlotSize, Theme,
 };
 
+/// Error returned by a task spawned with `FrameTag::thread_spawn`.
+/// `origin` names the widget type (and its handler) which spawned the task
+#[derive(Clone, Debug)]
+pub struct TaskError {
+    pub origin: String,
+    /// Id of the slot of the widget instance which spawned the task, see `SlotTag::id`
+    pub slot: Option<usize>,
+    pub error: Arc<crate::Error>,
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.slot {
+            Some(slot) => write!(f, "{} (slot {}): {}", self.origin, slot, self.error),
+            None => write!(f, "{}: {}", self.origin, self.error),
+        }
+    }
+}
+
 pub struct FrameShared {
-    thread_pool: ThreadPool,
+    executor: Executor,
     compositor: Compositor,
+    canvas_device: CanvasDevice,
+    graphics_device: CompositionGraphicsDevice,
     frame_visual: ContainerVisual,
+    error_senders: Vec<UnboundedSender<TaskError>>,
+    theme: Arc<Theme>,
+    animator: Arc<Mutex<Animator>>,
+    clock: Arc<Mutex<Clock>>,
+    /// Set by `FrameTag::request_layout`, the layout is updated on the next tick
+    /// or `run_until_stalled`
+    layout_requested: AtomicBool,
 }
+struct FocusTrap {
+    slot: SlotTag,
+    restore: Option<SlotTag>,
+}
+
 pub struct Frame {
     shared: Arc<RwLock<FrameShared>>,
     slots: Vec<SlotKeeper>,
+    focused: Option<SlotTag>,
+    focus_traps: Vec<FocusTrap>,
+    /// Root slots which block input to the slots below them
+    modal: Vec<SlotTag>,
 }
 
 impl Frame {
-    fn new(thread_pool: ThreadPool) -> crate::Result<Self> {
+    fn new(executor: Executor) -> crate::Result<Self> {
         let compositor = Compositor::new()?;
         let frame_visual = compositor.CreateContainerVisual()?;
+        let canvas_device = CanvasDevice::GetSharedDevice()?;
+        let graphics_device =
+            CanvasComposition::CreateCompositionGraphicsDevice(&compositor, &canvas_device)?;
         let shared = Arc::new(RwLock::new(FrameShared {
-            thread_pool,
+            executor,
             compositor,
+            canvas_device,
+            graphics_device,
             frame_visual,
+            error_senders: Vec::new(),
+            theme: Arc::new(Theme::default()),
+            animator: Arc::new(Mutex::new(Animator::default())),
+            clock: Arc::new(Mutex::new(Clock::new(ClockMode::Real))),
+            layout_requested: AtomicBool::new(false),
         }));
         Ok(Self {
             shared,
             slots: Vec::new(),
+            focused: None,
+            focus_traps: Vec::new(),
+            modal: Vec::new(),
         })
     }
     fn shared(&self) -> Arc<RwLock<FrameShared>> {
@@ -44,6 +118,14 @@ impl Frame {
         self.shared.read().unwrap().frame_visual.clone()
     }
 
+    fn subscribe_errors(&mut self) -> UnboundedReceiver<TaskError> {
+        let (sender, receiver) = unbounded();
+        let mut shared = self.shared.write().unwrap();
+        shared.error_senders.retain(|s| !s.is_closed());
+        shared.error_senders.push(sender);
+        receiver
+    }
+
     fn open_slot(&mut self) -> crate::Result<SlotTag> {
         let compositor = self.compositor();
         let container = compositor.CreateContainerVisual()?;
@@ -56,26 +138,247 @@ impl Frame {
             .InsertAtTop(container.clone())?;
         let slot_keeper = SlotKeeper::new(container)?;
         let slot = slot_keeper.tag();
+        tracing::info!(
+            slot = slot.id()?,
+            layer = self.slots.len(),
+            "frame slot opened"
+        );
         self.slots.push(slot_keeper);
         Ok(slot)
     }
 
+    /// Opens a slot above all others which takes all input: slots below it get neither
+    /// keys nor mouse events until it's closed
+    fn open_modal_slot(&mut self) -> crate::Result<SlotTag> {
+        let slot = self.open_slot()?;
+        self.modal.push(slot.clone());
+        Ok(slot)
+    }
+
+    /// Index of the first root slot which gets input: the topmost modal slot or the bottom one
+    fn input_layer(&self) -> usize {
+        self.slots
+            .iter()
+            .rposition(|slot| self.modal.contains(&slot.tag()))
+            .unwrap_or(0)
+    }
+
     pub fn close_slot(&mut self, slot: SlotTag) -> crate::Result<()> {
+        self.modal.retain(|modal| *modal != slot);
         if let Some(index) = self.slots.iter().position(|v| v.tag() == slot) {
             let slot = self.slots.remove(index);
+            tracing::info!(slot = slot.tag().id()?, layer = index, "frame slot closed");
             self.shared
                 .read()
                 .unwrap()
                 .frame_visual
                 .Children()?
                 .Remove(slot.container()?)?;
+            self.slot_removed(&slot.tag())?;
         }
         Ok(())
     }
+
+    /// Forgets focus and focus traps in the subtree of the slot removed from the tree
+    pub fn slot_removed(&mut self, slot: &SlotTag) -> crate::Result<()> {
+        self.focus_traps
+            .retain(|trap| !is_in_subtree(slot, &trap.slot));
+        if let Some(focused) = self.focused.clone() {
+            if is_in_subtree(slot, &focused) {
+                self.set_focus(None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends current size to the whole slot tree again, so that all widgets are arranged
+    /// with respect to the latest desired sizes of their content
+    pub fn layout(&mut self) -> crate::Result<()> {
+        let size = self.frame_visual().Size()?;
+        self.send_size(SlotSize(size))
+    }
+
+    pub fn theme(&self) -> Arc<Theme> {
+        self.shared.read().unwrap().theme.clone()
+    }
+
+    /// Replaces the theme and notifies all widgets, so that they restyle themselves
+    pub fn set_theme(&mut self, theme: Theme) -> crate::Result<()> {
+        self.send_theme_changed(ThemeChanged(Arc::new(theme)))
+    }
+
+    pub fn focused(&self) -> Option<SlotTag> {
+        self.focused.clone()
+    }
+
+    pub fn set_focus(&mut self, slot: Option<SlotTag>) -> crate::Result<()> {
+        if self.focused == slot {
+            return Ok(());
+        }
+        tracing::debug!(
+            slot = ?slot.as_ref().and_then(|s| s.id().ok()),
+            "focus changed"
+        );
+        self.focused = slot.clone();
+        for root in &mut self.slots {
+            root.send_focus_changed(FocusChanged(slot.clone()))?;
+        }
+        Ok(())
+    }
+
+    /// Restricts focus traversal and focus on click to the subtree of `slot` until
+    /// `release_focus` is called for it. Traps are nested: the last one wins.
+    pub fn trap_focus(&mut self, slot: SlotTag) -> crate::Result<()> {
+        let restore = self.focused.clone();
+        self.focus_traps.push(FocusTrap {
+            slot: slot.clone(),
+            restore,
+        });
+        let inside = self
+            .focused
+            .as_ref()
+            .map_or(false, |focused| is_in_subtree(&slot, focused));
+        if !inside {
+            let first = self.focus_chain().into_iter().next();
+            self.set_focus(first)?;
+        }
+        Ok(())
+    }
+
+    pub fn release_focus(&mut self, slot: SlotTag) -> crate::Result<()> {
+        if let Some(index) = self.focus_traps.iter().position(|t| t.slot == slot) {
+            let trap = self.focus_traps.remove(index);
+            if index == self.focus_traps.len() {
+                self.set_focus(trap.restore)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn focus_roots(&self) -> Vec<SlotTag> {
+        if let Some(trap) = self.focus_traps.last() {
+            vec![trap.slot.clone()]
+        } else {
+            self.slots.iter().map(|s| s.tag()).collect()
+        }
+    }
+
+    /// Focusable slots in tree order, limited by the active focus trap
+    fn focus_chain(&self) -> Vec<SlotTag> {
+        let mut chain = Vec::new();
+        for root in self.focus_roots() {
+            collect_focusable(&root, &mut chain);
+        }
+        chain
+    }
+
+    fn move_focus(&mut self, backward: bool) -> crate::Result<()> {
+        let chain = self.focus_chain();
+        if chain.is_empty() {
+            return Ok(());
+        }
+        let len = chain.len();
+        let current = self
+            .focused
+            .as_ref()
+            .and_then(|focused| chain.iter().position(|s| s == focused));
+        let next = match (current, backward) {
+            (Some(index), false) => (index + 1) % len,
+            (Some(index), true) => (index + len - 1) % len,
+            (None, false) => 0,
+            (None, true) => len - 1,
+        };
+        self.set_focus(Some(chain[next].clone()))
+    }
+
+    /// Index of the topmost visible root slot, which gets the positioned mouse events
+    fn top_visible_slot(&self) -> Option<usize> {
+        self.slots
+            .iter()
+            .rposition(|slot| is_visible(&slot.tag()))
+            .filter(|&index| index >= self.input_layer())
+    }
+
+    /// Slots under the point in the topmost layer from `layer` up which has any
+    fn hit_test_from(&self, layer: usize, point: Vector2) -> crate::Result<Vec<Hit>> {
+        for slot in self.slots[layer..].iter().rev() {
+            let chain = slot.tag().hit_test(point)?;
+            if !chain.is_empty() {
+                return Ok(chain);
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    /// Slots under the point given in frame coordinates, from the root slot of the topmost
+    /// layer having any down to the deepest one, each with the point in its coordinates
+    pub fn hit_test(&self, point: Vector2) -> crate::Result<Vec<Hit>> {
+        self.hit_test_from(0, point)
+    }
+
+    /// Geometry and state of the whole slot tree, for diagnostics
+    pub fn inspect(&self) -> crate::Result<FrameInfo> {
+        let size = self.frame_visual().Size()?;
+        Ok(FrameInfo {
+            size: (size.X, size.Y),
+            input_layer: self.input_layer(),
+            slots: self
+                .slots
+                .iter()
+                .map(|slot| SlotInfo::collect(&slot.tag()))
+                .collect::<crate::Result<_>>()?,
+        })
+    }
+
+    fn focus_at_point(&mut self, point: Vector2) -> crate::Result<()> {
+        // Slots below a modal layer can't be focused by the mouse
+        let target = self
+            .hit_test_from(self.input_layer(), point)?
+            .into_iter()
+            .rev()
+            .map(|hit| hit.slot)
+            .find(|slot| slot.is_focusable().unwrap_or(false));
+        if let Some(trap) = self.focus_traps.last().map(|t| t.slot.clone()) {
+            match target {
+                Some(target) if is_in_subtree(&trap, &target) => self.set_focus(Some(target)),
+                _ => Ok(()),
+            }
+        } else {
+            self.set_focus(target)
+        }
+    }
+}
+
+/// Slots destroyed by their owners are treated as hidden
+fn is_visible(slot: &SlotTag) -> bool {
+    slot.is_visible().unwrap_or(false)
+}
+
+fn collect_focusable(slot: &SlotTag, chain: &mut Vec<SlotTag>) {
+    // Content of hidden slots can't be focused
+    if !is_visible(slot) {
+        return;
+    }
+    if let Ok(true) = slot.is_focusable() {
+        chain.push(slot.clone());
+    }
+    for child in slot.children().unwrap_or_default() {
+        collect_focusable(&child, chain);
+    }
+}
+
+fn is_in_subtree(root: &SlotTag, slot: &SlotTag) -> bool {
+    root == slot
+        || root
+            .children()
+            .unwrap_or_default()
+            .iter()
+            .any(|child| is_in_subtree(child, slot))
 }
 
 impl SendSlotEvent for Frame {
     fn send_size(&mut self, size: SlotSize) -> crate::Result<()> {
+        tracing::debug!(size = ?size.0, "frame size");
         self.frame_visual().SetSize(size.0)?;
         for slot in &mut self.slots {
             slot.send_size(size.clone())?;
@@ -84,7 +387,9 @@ impl SendSlotEvent for Frame {
     }
 
     fn send_mouse_left_pressed(&mut self, event: MouseLeftPressed) -> crate::Result<()> {
-        for slot in &mut self.slots {
+        tracing::debug!(point = ?event.0, "frame mouse left pressed");
+        let input_layer = self.input_layer();
+        for slot in &mut self.slots[input_layer..] {
             slot.send_mouse_left_pressed(event.clone())?;
         }
         Ok(())
@@ -94,8 +399,36 @@ impl SendSlotEvent for Frame {
         &mut self,
         event: MouseLeftPressedFocused,
     ) -> crate::Result<()> {
-        if let Some(slot) = self.slots.last_mut() {
-            slot.send_mouse_left_pressed_focused(event)?;
+        let layer = self.top_visible_slot();
+        tracing::debug!(point = ?event.0, layer = ?layer, "frame mouse left pressed focused");
+        self.focus_at_point(event.0)?;
+        if let Some(layer) = layer {
+            self.slots[layer].send_mouse_left_pressed_focused(event)?;
+        }
+        Ok(())
+    }
+
+    fn send_key_pressed(&mut self, event: KeyPressed) -> crate::Result<()> {
+        tracing::debug!(key = ?event.key, shift = event.shift, "frame key pressed");
+        if event.key == Key::Tab {
+            return self.move_focus(event.shift);
+        }
+        let input_layer = self.input_layer();
+        for slot in &mut self.slots[input_layer..] {
+            slot.send_key_pressed(event.clone())?;
+        }
+        Ok(())
+    }
+
+    fn send_focus_changed(&mut self, event: FocusChanged) -> crate::Result<()> {
+        self.set_focus(event.0)
+    }
+
+    fn send_theme_changed(&mut self, event: ThemeChanged) -> crate::Result<()> {
+        tracing::info!(theme = %event.0.name, "theme changed");
+        self.shared.write().unwrap().theme = event.0.clone();
+        for slot in &mut self.slots {
+            slot.send_theme_changed(event.clone())?;
         }
         Ok(())
     }
@@ -105,8 +438,9 @@ impl SendSlotEvent for Frame {
 pub struct FrameKeeper(Keeper<Frame, FrameShared>);
 
 impl FrameKeeper {
-    pub fn new(thread_pool: ThreadPool) -> crate::Result<Self> {
-        let frame = Frame::new(thread_pool)?;
+    /// Creates frame running its tasks on the given executor: `ThreadPool` or `ManualExecutor`
+    pub fn new(executor: impl Into<Executor>) -> crate::Result<Self> {
+        let frame = Frame::new(executor.into())?;
         let shared = frame.shared();
         let keeper = Keeper::new_with_shared(frame, shared);
         Ok(Self(keeper))
@@ -132,24 +466,234 @@ impl FrameTag {
     pub fn frame_visual(&self) -> crate::Result<ContainerVisual> {
         Ok(self.0.read_shared(|v| v.frame_visual.clone())?)
     }
-    pub fn thread_pool(&self) -> crate::Result<ThreadPool> {
-        Ok(self.0.read_shared(|v| v.thread_pool.clone())?)
+    pub fn canvas_device(&self) -> crate::Result<CanvasDevice> {
+        Ok(self.0.read_shared(|v| v.canvas_device.clone())?)
+    }
+    /// Device for creating drawing surfaces to render into with Win2D
+    pub fn graphics_device(&self) -> crate::Result<CompositionGraphicsDevice> {
+        Ok(self.0.read_shared(|v| v.graphics_device.clone())?)
+    }
+    pub fn executor(&self) -> crate::Result<Executor> {
+        Ok(self.0.read_shared(|v| v.executor.clone())?)
     }
-    pub fn thread_spawn<Fut>(&self, future: Fut) -> crate::Result<()>
+    /// Runs ready tasks when frame uses `ManualExecutor`. Returns false if there was nothing to run
+    pub fn run_until_stalled(&self) -> crate::Result<bool> {
+        // The executor is cloned out of the frame: tasks being polled need access to it
+        let executor = self.executor()?;
+        let mut progress = false;
+        // Layout sends events to widgets, their handlers may request layout again
+        loop {
+            progress |= executor.run_until_stalled();
+            if !self.update_layout()? {
+                return Ok(progress);
+            }
+            progress = true;
+        }
+    }
+    /// Runs the future on the frame's executor. If the future fails, the error
+    /// is delivered to subscribers of `on_error`, marked with `origin`
+    pub fn thread_spawn<Fut>(&self, origin: impl Into<String>, future: Fut) -> crate::Result<()>
+    where
+        Fut: Future<Output = crate::Result<()>> + Send + 'static,
+    {
+        self.slot_spawn(None, origin, future)
+    }
+    /// Spawns the task on behalf of the widget in the slot, errors of the task tell the slot
+    pub(crate) fn slot_spawn<Fut>(
+        &self,
+        slot: Option<usize>,
+        origin: impl Into<String>,
+        future: Fut,
+    ) -> crate::Result<()>
     where
         Fut: Future<Output = crate::Result<()>> + Send + 'static,
     {
-        self.thread_pool()?.spawn_ok(async {
-            future.await.unwrap() // TODO: store error somethere (thread_local? special inrerface in tag?)
+        let frame = self.clone();
+        let origin = origin.into();
+        self.executor()?.spawn_ok(async move {
+            if let Err(error) = future.await {
+                frame.report_error(TaskError {
+                    origin,
+                    slot,
+                    error: Arc::new(error),
+                });
+            }
         });
         Ok(())
     }
+    /// Stream of errors of tasks spawned with `thread_spawn`. Each subscriber
+    /// receives all errors reported after subscription
+    pub fn on_error(&self) -> crate::Result<UnboundedReceiver<TaskError>> {
+        Ok(self.0.call_mut(|frame| frame.subscribe_errors())?)
+    }
+    fn report_error(&self, error: TaskError) {
+        let senders = self
+            .0
+            .read_shared(|v| v.error_senders.clone())
+            .unwrap_or_default();
+        let mut delivered = false;
+        for sender in senders {
+            delivered |= sender.unbounded_send(error.clone()).is_ok();
+        }
+        if !delivered {
+            // Nobody listens (or frame is already destroyed): don't lose the error completely
+            tracing::error!(
+                origin = %error.origin,
+                slot = ?error.slot,
+                error = %error.error,
+                "unhandled task error"
+            );
+        }
+    }
+    fn animator(&self) -> crate::Result<Arc<Mutex<Animator>>> {
+        Ok(self.0.read_shared(|v| v.animator.clone())?)
+    }
+    /// Starts the animation of the property. Animations progress with the frame's clock
+    pub fn animate(
+        &self,
+        target: AnimationTarget,
+        to: AnimationValue,
+        transition: Transition,
+    ) -> crate::Result<AnimationId> {
+        self.animator()?
+            .lock()
+            .unwrap()
+            .animate(target, to, transition)
+    }
+    /// Jumps to the end of the animation. Does nothing if it's already over
+    pub fn finish_animation(&self, id: AnimationId) -> crate::Result<()> {
+        self.animator()?.lock().unwrap().finish(id)?;
+        Ok(())
+    }
+    /// Resolves when all the animations are over: completed, fast-forwarded or replaced
+    /// by another animation of the same property. Similar to `CompositionScopedBatch`
+    pub fn animations_finished(
+        &self,
+        ids: &[AnimationId],
+    ) -> crate::Result<impl Future<Output = ()> + Send + 'static> {
+        let animator = self.animator()?;
+        let mut animator = animator.lock().unwrap();
+        let finished = ids
+            .iter()
+            .map(|&id| animator.finished(id))
+            .collect::<Vec<_>>();
+        // Dropped senders (e.g. the frame is destroyed) also mean the end of animations
+        Ok(join_all(finished).map(|_| ()))
+    }
+    /// Jumps to the end of all running animations
+    pub fn finish_animations(&self) -> crate::Result<()> {
+        self.animator()?.lock().unwrap().finish_all()?;
+        Ok(())
+    }
+    fn clock(&self) -> crate::Result<Arc<Mutex<Clock>>> {
+        Ok(self.0.read_shared(|v| v.clock.clone())?)
+    }
+    pub fn clock_mode(&self) -> crate::Result<ClockMode> {
+        Ok(self.clock()?.lock().unwrap().mode())
+    }
+    /// Switching to `Virtual` stops the frame time, e.g. for tests
+    pub fn set_clock_mode(&self, mode: ClockMode) -> crate::Result<()> {
+        self.clock()?.lock().unwrap().set_mode(mode);
+        Ok(())
+    }
+    /// Frame time in seconds
+    pub fn now(&self) -> crate::Result<f64> {
+        Ok(self.clock()?.lock().unwrap().now())
+    }
+    /// Advances the real clock by the time passed since the previous call. Should be
+    /// called by the window's timer. Does nothing if the clock is virtual
+    pub fn tick(&self) -> crate::Result<()> {
+        self.update_layout()?;
+        let dt = self.clock()?.lock().unwrap().tick();
+        match dt {
+            Some(dt) => self.advance_time(dt),
+            None => Ok(()),
+        }
+    }
+    /// Moves the frame time forward by `dt` seconds: animations are advanced first,
+    /// then timers fire and tick subscribers are notified
+    pub fn advance_time(&self, dt: f32) -> crate::Result<()> {
+        self.animator()?.lock().unwrap().advance(dt)?;
+        self.clock()?.lock().unwrap().advance(dt);
+        Ok(())
+    }
+    /// Stream of ticks of the frame's clock, about one per displayed frame with the real clock
+    pub fn on_tick(&self) -> crate::Result<UnboundedReceiver<Tick>> {
+        Ok(self.clock()?.lock().unwrap().on_tick())
+    }
+    /// Resolves when `delay` of frame time passes
+    pub fn timer(
+        &self,
+        delay: Duration,
+    ) -> crate::Result<impl Future<Output = ()> + Send + 'static> {
+        let fired = self.clock()?.lock().unwrap().timer(delay);
+        // The clock is gone with the frame, so there is nothing to wait for
+        Ok(fired.map(|_| ()))
+    }
+    /// Stream of ticks each `period` of frame time
+    pub fn interval(&self, period: Duration) -> crate::Result<UnboundedReceiver<Tick>> {
+        Ok(self.clock()?.lock().unwrap().interval(period))
+    }
     pub fn open_slot(&self) -> crate::Result<SlotTag> {
         self.0.call_mut(|frame| frame.open_slot())?
     }
+    /// Slots under the point, from the root slot down to the deepest one, see `Frame::hit_test`
+    pub fn hit_test(&self, point: Vector2) -> crate::Result<Vec<Hit>> {
+        self.0.call(|frame| frame.hit_test(point))?
+    }
+    /// Snapshot of the slot tree which can be dumped as text or JSON, see `FrameInfo`
+    pub fn inspect(&self) -> crate::Result<FrameInfo> {
+        self.0.call(|frame| frame.inspect())?
+    }
+    pub fn open_modal_slot(&self) -> crate::Result<SlotTag> {
+        self.0.call_mut(|frame| frame.open_modal_slot())?
+    }
+    /// Must be called by widgets removing a child slot, so that the focus doesn't stay
+    /// on a detached slot
+    pub fn slot_removed(&self, slot: &SlotTag) -> crate::Result<()> {
+        self.0.call_mut(|frame| frame.slot_removed(slot))?
+    }
     pub fn close_slot(&self, slot: SlotTag) -> crate::Result<()> {
         self.0.call_mut(|frame| frame.close_slot(slot))?
     }
+    /// Marks the layout outdated, e.g. when a widget's desired size changes. The layout is
+    /// updated once on the next tick or `run_until_stalled`, not while the caller holds
+    /// its own widget locked
+    pub fn request_layout(&self) -> crate::Result<()> {
+        self.0
+            .read_shared(|v| v.layout_requested.store(true, Ordering::SeqCst))?;
+        Ok(())
+    }
+    /// Updates the layout if it was requested. Returns false if there was nothing to do
+    pub fn update_layout(&self) -> crate::Result<bool> {
+        let requested = self
+            .0
+            .read_shared(|v| v.layout_requested.swap(false, Ordering::SeqCst))?;
+        if requested {
+            tracing::trace!("frame layout");
+            self.0.call_mut(|frame| frame.layout())??;
+        }
+        Ok(requested)
+    }
+    /// Current theme. Widgets keep the theme they got and update it on `ThemeChanged`
+    pub fn theme(&self) -> crate::Result<Arc<Theme>> {
+        Ok(self.0.read_shared(|v| v.theme.clone())?)
+    }
+    pub fn set_theme(&self, theme: Theme) -> crate::Result<()> {
+        self.0.call_mut(|frame| frame.set_theme(theme))?
+    }
+    pub fn focused(&self) -> crate::Result<Option<SlotTag>> {
+        Ok(self.0.call(|frame| frame.focused())?)
+    }
+    pub fn set_focus(&self, slot: Option<SlotTag>) -> crate::Result<()> {
+        self.0.call_mut(|frame| frame.set_focus(slot))?
+    }
+    pub fn trap_focus(&self, slot: SlotTag) -> crate::Result<()> {
+        self.0.call_mut(|frame| frame.trap_focus(slot))?
+    }
+    pub fn release_focus(&self, slot: SlotTag) -> crate::Result<()> {
+        self.0.call_mut(|frame| frame.release_focus(slot))?
+    }
 }
 
 impl SendSlotEvent for FrameTag {
@@ -169,4 +713,251 @@ impl SendSlotEvent for FrameTag {
         self.0
             .call_mut(|frame| frame.send_mouse_left_pressed_focused(event))?
     }
+
+    fn send_key_pressed(&mut self, event: KeyPressed) -> crate::Result<()> {
+        self.0.call_mut(|frame| frame.send_key_pressed(event))?
+    }
+
+    fn send_focus_changed(&mut self, event: FocusChanged) -> crate::Result<()> {
+        self.0.call_mut(|frame| frame.send_focus_changed(event))?
+    }
+
+    fn send_theme_changed(&mut self, event: ThemeChanged) -> crate::Result<()> {
+        self.0.call_mut(|frame| frame.send_theme_changed(event))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bindings::Windows::{
+        Foundation::Numerics::{Vector2, Vector3},
+        System::DispatcherQueueController,
+        Win32::System::WinRT::{
+            CreateDispatcherQueueController, DispatcherQueueOptions, RoInitialize, DQTAT_COM_NONE,
+            DQTYPE_THREAD_CURRENT, RO_INIT_SINGLETHREADED,
+        },
+    };
+    use futures::{FutureExt, Stream, StreamExt};
+
+    use super::{FrameKeeper, FrameTag};
+    use crate::{
+        CellLimit, Dialog, Hit, Key, KeyPressed, ManualExecutor, MouseLeftPressed,
+        ReceiveSlotEvent, SendSlotEvent, SlotKeeper, SlotSize, SlotTag,
+    };
+
+    /// Frame running its tasks on the test's thread. The compositor needs a dispatcher
+    /// queue on the thread, which lives as long as its controller
+    fn frame() -> (DispatcherQueueController, FrameKeeper) {
+        // Fails harmlessly if the thread is already initialized
+        let _ = unsafe { RoInitialize(RO_INIT_SINGLETHREADED) };
+        let options = DispatcherQueueOptions {
+            dwSize: std::mem::size_of::<DispatcherQueueOptions>() as u32,
+            threadType: DQTYPE_THREAD_CURRENT,
+            apartmentType: DQTAT_COM_NONE,
+        };
+        let controller = unsafe { CreateDispatcherQueueController(options) }.unwrap();
+        let frame = FrameKeeper::new(ManualExecutor::new()).unwrap();
+        frame
+            .tag()
+            .send_size(SlotSize(Vector2 { X: 100., Y: 100. }))
+            .unwrap();
+        (controller, frame)
+    }
+
+    fn key(key: Key) -> KeyPressed {
+        KeyPressed { key, shift: false }
+    }
+
+    fn click() -> MouseLeftPressed {
+        MouseLeftPressed(point(10., 10.))
+    }
+
+    fn point(x: f32, y: f32) -> Vector2 {
+        Vector2 { X: x, Y: y }
+    }
+
+    /// Child slot of `parent` placed at `offset`, on top of the parent's other children
+    fn child(
+        frame: &FrameTag,
+        parent: &SlotTag,
+        offset: (f32, f32),
+        size: (f32, f32),
+    ) -> SlotKeeper {
+        let container = frame.compositor().unwrap().CreateContainerVisual().unwrap();
+        container
+            .SetOffset(Vector3 {
+                X: offset.0,
+                Y: offset.1,
+                Z: 0.,
+            })
+            .unwrap();
+        container.SetSize(point(size.0, size.1)).unwrap();
+        parent
+            .container()
+            .unwrap()
+            .Children()
+            .unwrap()
+            .InsertAtTop(container.clone())
+            .unwrap();
+        let keeper = SlotKeeper::new(container).unwrap();
+        parent.add_child(keeper.tag()).unwrap();
+        keeper
+    }
+
+    fn hit_slots(frame: &FrameTag, x: f32, y: f32) -> Vec<SlotTag> {
+        frame
+            .hit_test(point(x, y))
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.slot)
+            .collect()
+    }
+
+    /// Number of events ready in the stream. Streams subscribe when first polled
+    fn received(stream: &mut (impl Stream + Unpin)) -> usize {
+        let mut count = 0;
+        while let Some(Some(_)) = stream.next().now_or_never() {
+            count += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn modal_layer_blocks_input_below() {
+        let (_controller, frame) = frame();
+        let mut tag = frame.tag();
+        let below = tag.open_slot().unwrap();
+        let modal = tag.open_modal_slot().unwrap();
+        let above = tag.open_slot().unwrap();
+        let mut keys = [&below, &modal, &above].map(|slot| slot.on_key_pressed());
+        let mut clicks = [&below, &modal, &above].map(|slot| slot.on_mouse_left_pressed());
+        let mut received_all = || {
+            (
+                keys.iter_mut().map(received).collect::<Vec<_>>(),
+                clicks.iter_mut().map(received).collect::<Vec<_>>(),
+            )
+        };
+        received_all();
+
+        tag.send_key_pressed(key(Key::Enter)).unwrap();
+        tag.send_mouse_left_pressed(click()).unwrap();
+        assert_eq!(received_all(), (vec![0, 1, 1], vec![0, 1, 1]));
+
+        tag.close_slot(modal.clone()).unwrap();
+        assert!(frame.get().modal.is_empty());
+        assert_eq!(frame.get().input_layer(), 0);
+        tag.send_key_pressed(key(Key::Enter)).unwrap();
+        tag.send_mouse_left_pressed(click()).unwrap();
+        assert_eq!(received_all(), (vec![1, 0, 1], vec![1, 0, 1]));
+    }
+
+    #[test]
+    fn topmost_modal_layer_gets_input() {
+        let (_controller, frame) = frame();
+        let tag = frame.tag();
+        tag.open_slot().unwrap();
+        let first = tag.open_modal_slot().unwrap();
+        tag.open_slot().unwrap();
+        let second = tag.open_modal_slot().unwrap();
+        assert_eq!(frame.get().input_layer(), 3);
+        tag.close_slot(second).unwrap();
+        assert_eq!(frame.get().input_layer(), 1);
+        assert!(frame.get().modal == vec![first]);
+    }
+
+    #[test]
+    fn escape_cancels_dialog() {
+        let (_controller, frame) = frame();
+        let mut tag = frame.tag();
+        let dialog = Dialog::<()>::open(tag.clone(), CellLimit::default()).unwrap();
+        // Focus is trapped before anyone waits for the result
+        assert_eq!(frame.get().focus_traps.len(), 1);
+        let handle = dialog.handle();
+        let mut result = Box::pin(dialog.result());
+        tag.run_until_stalled().unwrap();
+        assert!((&mut result).now_or_never().is_none());
+
+        tag.send_key_pressed(key(Key::Escape)).unwrap();
+        tag.run_until_stalled().unwrap();
+        assert_eq!(result.now_or_never().unwrap().unwrap(), None);
+        assert!(!handle.cancel(), "dialog should be closed already");
+        // Closing the dialog removes its layer and the focus trap
+        assert_eq!(frame.get().slots.len(), 0);
+        assert!(frame.get().modal.is_empty() && frame.get().focus_traps.is_empty());
+    }
+
+    #[test]
+    fn hit_test_translates_point_to_each_slot() {
+        let (_controller, frame) = frame();
+        let tag = frame.tag();
+        let root = tag.open_slot().unwrap();
+        let scaled = child(&tag, &root, (10., 20.), (50., 50.));
+        scaled
+            .container()
+            .unwrap()
+            .SetScale(Vector3 {
+                X: 2.,
+                Y: 2.,
+                Z: 1.,
+            })
+            .unwrap();
+        assert_eq!(
+            tag.hit_test(point(30., 40.)).unwrap(),
+            vec![
+                Hit {
+                    slot: root,
+                    point: point(30., 40.),
+                },
+                Hit {
+                    slot: scaled.tag(),
+                    point: point(10., 10.),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn hidden_slots_are_not_hit() {
+        let (_controller, frame) = frame();
+        let tag = frame.tag();
+        let root = tag.open_slot().unwrap();
+        let cover = child(&tag, &root, (0., 0.), (50., 50.));
+        assert_eq!(hit_slots(&tag, 10., 10.), vec![root.clone(), cover.tag()]);
+        cover.tag().set_visible(false).unwrap();
+        assert_eq!(hit_slots(&tag, 10., 10.), vec![root.clone()]);
+        root.set_visible(false).unwrap();
+        assert!(hit_slots(&tag, 10., 10.).is_empty());
+    }
+
+    #[test]
+    fn clipping_slots_prune_content_outside_of_them() {
+        let (_controller, frame) = frame();
+        let tag = frame.tag();
+        let root = tag.open_slot().unwrap();
+        let parent = child(&tag, &root, (0., 0.), (20., 20.));
+        let outside = child(&tag, &parent.tag(), (30., 30.), (10., 10.));
+        // Content out of a slot which doesn't clip is hit through it
+        assert_eq!(
+            hit_slots(&tag, 35., 35.),
+            vec![root.clone(), parent.tag(), outside.tag()]
+        );
+        assert_eq!(hit_slots(&tag, 25., 25.), vec![root.clone()]);
+        parent.tag().set_clip_to_bounds(true).unwrap();
+        assert_eq!(hit_slots(&tag, 35., 35.), vec![root]);
+    }
+
+    #[test]
+    fn later_children_and_layers_are_on_top() {
+        let (_controller, frame) = frame();
+        let tag = frame.tag();
+        let root = tag.open_slot().unwrap();
+        let below = child(&tag, &root, (0., 0.), (50., 50.));
+        let above = child(&tag, &root, (25., 25.), (50., 50.));
+        assert_eq!(hit_slots(&tag, 30., 30.), vec![root.clone(), above.tag()]);
+        assert_eq!(hit_slots(&tag, 10., 10.), vec![root.clone(), below.tag()]);
+        let layer = tag.open_slot().unwrap();
+        assert_eq!(hit_slots(&tag, 10., 10.), vec![layer.clone()]);
+        layer.set_visible(false).unwrap();
+        assert_eq!(hit_slots(&tag, 10., 10.), vec![root, below.tag()]);
+    }
 }
@@ -0,0 +1,208 @@
+use std::sync::{Arc, RwLockReadGuard, RwLockWriteGuard};
+
+use async_object::{Keeper, Tag};
+use bindings::{
+    Microsoft::Graphics::Canvas::{CanvasBitmap, UI::Composition::CanvasComposition},
+    Windows::{
+        Foundation::{Numerics::Vector2, Size},
+        Graphics::DirectX::{DirectXAlphaMode, DirectXPixelFormat},
+        UI::{
+            Colors,
+            Composition::{
+                CompositionDrawingSurface, CompositionStretch, CompositionSurfaceBrush,
+                SpriteVisual,
+            },
+        },
+    },
+};
+use futures::{Future, StreamExt};
+use serde::Deserialize;
+
+use crate::{slot::SlotPlug, Bitmap, FrameTag, ReceiveSlotEvent, SlotTag, TaskGroup};
+
+/// How the image is scaled to the slot
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+pub enum ImageFit {
+    /// Whole image is shown as large as possible keeping its aspect ratio
+    #[default]
+    Fit,
+    /// Image covers the whole slot keeping its aspect ratio, the overflow is cut off
+    Fill,
+    /// Image covers the whole slot, the aspect ratio is not kept
+    Stretch,
+    /// Image is shown in its own size in the middle of the slot
+    Center,
+}
+
+impl ImageFit {
+    fn stretch(self) -> CompositionStretch {
+        match self {
+            ImageFit::Fit => CompositionStretch::Uniform,
+            ImageFit::Fill => CompositionStretch::UniformToFill,
+            ImageFit::Stretch => CompositionStretch::Fill,
+            ImageFit::Center => CompositionStretch::None,
+        }
+    }
+}
+
+/// Copies the bitmap to a surface of the bitmap's size
+pub(crate) fn bitmap_surface(
+    frame: &FrameTag,
+    bitmap: &Bitmap,
+) -> crate::Result<CompositionDrawingSurface> {
+    let surface = frame.graphics_device()?.CreateDrawingSurface(
+        Size {
+            Width: bitmap.width().max(1) as f32,
+            Height: bitmap.height().max(1) as f32,
+        },
+        DirectXPixelFormat::B8G8R8A8UIntNormalized,
+        DirectXAlphaMode::Premultiplied,
+    )?;
+    let session = CanvasComposition::CreateDrawingSession(&surface)?;
+    session.Clear(Colors::Transparent()?)?;
+    if !bitmap.is_empty() {
+        let canvas_bitmap = CanvasBitmap::CreateFromBytes(
+            &frame.canvas_device()?,
+            bitmap.pixels(),
+            bitmap.width() as i32,
+            bitmap.height() as i32,
+            DirectXPixelFormat::R8G8B8A8UIntNormalized,
+        )?;
+        session.DrawImageAtOrigin(&canvas_bitmap)?;
+    }
+    session.Close()?;
+    Ok(surface)
+}
+
+pub struct Image {
+    frame: FrameTag,
+    slot: SlotPlug,
+    visual: SpriteVisual,
+    brush: CompositionSurfaceBrush,
+    bitmap: Arc<Bitmap>,
+    fit: ImageFit,
+    tasks: TaskGroup,
+}
+
+impl Image {
+    fn new(
+        frame: FrameTag,
+        slot: SlotTag,
+        bitmap: Arc<Bitmap>,
+        fit: ImageFit,
+    ) -> crate::Result<Self> {
+        let compositor = frame.compositor()?;
+        let visual = compositor.CreateSpriteVisual()?;
+        visual.SetSize(slot.container()?.Size()?)?;
+        let brush = compositor.CreateSurfaceBrush()?;
+        brush.SetStretch(fit.stretch())?;
+        visual.SetBrush(brush.clone())?;
+        let slot = slot.plug("Image", visual.clone().into())?;
+        let tasks = TaskGroup::for_slot(frame.clone(), &slot.tag())?;
+        let mut image = Self {
+            frame,
+            slot,
+            visual,
+            brush,
+            bitmap,
+            fit,
+            tasks,
+        };
+        image.redraw()?;
+        Ok(image)
+    }
+
+    fn set_bitmap(&mut self, bitmap: Arc<Bitmap>) -> crate::Result<()> {
+        self.bitmap = bitmap;
+        self.redraw()
+    }
+
+    fn set_fit(&mut self, fit: ImageFit) -> crate::Result<()> {
+        self.fit = fit;
+        self.brush.SetStretch(fit.stretch())?;
+        Ok(())
+    }
+
+    fn set_size(&mut self, size: Vector2) -> crate::Result<()> {
+        tracing::trace!(size = ?size, "image size");
+        // The brush scales the image within the visual, so the visual just covers the slot
+        self.visual.SetSize(size)?;
+        Ok(())
+    }
+
+    /// Shows the bitmap in its own size and reports this size as desired size of the slot
+    fn redraw(&mut self) -> crate::Result<()> {
+        let surface = bitmap_surface(&self.frame, &self.bitmap)?;
+        self.brush.SetSurface(&surface)?;
+        let image_size = Vector2 {
+            X: self.bitmap.width() as f32,
+            Y: self.bitmap.height() as f32,
+        };
+        if self.slot.tag().set_desired_size(Some(image_size))? {
+            tracing::debug!(desired_size = ?image_size, "image desired size changed");
+            self.frame.request_layout()?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct ImageKeeper(Keeper<Image>);
+
+impl ImageKeeper {
+    /// The bitmap is shared, so the same decoded image can be shown in many slots
+    pub fn new(
+        frame: FrameTag,
+        slot: SlotTag,
+        bitmap: Arc<Bitmap>,
+        fit: ImageFit,
+    ) -> crate::Result<Self> {
+        let keeper = Self(Keeper::new(Image::new(frame, slot, bitmap, fit)?));
+        keeper.spawn_event_handlers()?;
+        Ok(keeper)
+    }
+    pub fn tag(&self) -> ImageTag {
+        ImageTag(self.0.tag())
+    }
+    pub fn get(&self) -> RwLockReadGuard<'_, Image> {
+        self.0.get()
+    }
+    pub fn get_mut(&self) -> RwLockWriteGuard<'_, Image> {
+        self.0.get_mut()
+    }
+    /// Resolves when event handlers of the image are stopped, i.e. after all keepers are dropped
+    pub fn tasks_finished(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.get().tasks.finished()
+    }
+    fn spawn_event_handlers(&self) -> crate::Result<()> {
+        let tag = self.tag();
+        let slot = self.get().slot.tag();
+        self.get_mut().tasks.spawn("Image::on_size", async move {
+            while let Some(size) = slot.on_size().next().await {
+                tag.set_size(size.0)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ImageTag(Tag<Image>);
+
+impl ImageTag {
+    pub fn bitmap(&self) -> crate::Result<Arc<Bitmap>> {
+        Ok(self.0.call(|v| v.bitmap.clone())?)
+    }
+    pub fn set_bitmap(&self, bitmap: Arc<Bitmap>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_bitmap(bitmap))??)
+    }
+    pub fn fit(&self) -> crate::Result<ImageFit> {
+        Ok(self.0.call(|v| v.fit)?)
+    }
+    pub fn set_fit(&self, fit: ImageFit) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_fit(fit))??)
+    }
+    pub fn set_size(&self, size: Vector2) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_size(size))??)
+    }
+}
@@ -0,0 +1,412 @@
+//! Geometry of the slot tree for diagnosing layout: a dump as text or JSON and an overlay
+//! outlining every slot. The dump is plain data, so it doesn't depend on the composition backend
+
+use std::{
+    fmt::Write,
+    sync::{RwLockReadGuard, RwLockWriteGuard},
+    time::Duration,
+};
+
+use async_object::{Keeper, Tag};
+use bindings::{
+    Microsoft::Graphics::Canvas::{Text::CanvasTextFormat, UI::Composition::CanvasComposition},
+    Windows::{
+        Foundation::{Numerics::Vector2, Size},
+        Graphics::DirectX::{DirectXAlphaMode, DirectXPixelFormat},
+        UI::{Colors, Composition::SpriteVisual},
+    },
+};
+use futures::{Future, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{layout::Rect, FrameTag, SlotTag, TaskGroup};
+
+/// How often the overlay follows changes of the layout
+const REFRESH_PERIOD: Duration = Duration::from_millis(250);
+const LABEL_FONT_SIZE: f32 = 10.;
+
+/// Geometry and state of the slot and its children
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SlotInfo {
+    pub id: usize,
+    /// Types of the widgets plugged into the slot, e.g. a background under a ribbon
+    pub widgets: Vec<String>,
+    /// Top left corner in coordinates of the parent slot, with the scale applied
+    pub offset: (f32, f32),
+    pub size: (f32, f32),
+    pub scale: (f32, f32),
+    pub desired_size: Option<(f32, f32)>,
+    pub visible: bool,
+    pub clip_to_bounds: bool,
+    pub focusable: bool,
+    pub focused: bool,
+    pub children: Vec<SlotInfo>,
+}
+
+impl SlotInfo {
+    /// Snapshot of the slot's subtree. Children destroyed by their owners are left out
+    pub fn collect(slot: &SlotTag) -> crate::Result<Self> {
+        let container = slot.container()?;
+        let offset = container.Offset()?;
+        let size = container.Size()?;
+        let scale = container.Scale()?;
+        let center = container.CenterPoint()?;
+        let children = slot
+            .children()?
+            .iter()
+            .filter_map(|child| Self::collect(child).ok())
+            .collect();
+        Ok(Self {
+            id: slot.id()?,
+            widgets: slot.widgets()?.into_iter().map(String::from).collect(),
+            offset: (
+                offset.X + center.X * (1. - scale.X),
+                offset.Y + center.Y * (1. - scale.Y),
+            ),
+            size: (size.X, size.Y),
+            scale: (scale.X, scale.Y),
+            desired_size: slot.desired_size()?.map(|size| (size.X, size.Y)),
+            visible: container.IsVisible()?,
+            clip_to_bounds: slot.clips_to_bounds()?,
+            focusable: slot.is_focusable()?,
+            focused: slot.is_focused()?,
+            children,
+        })
+    }
+
+    /// Id, widgets, size and offset, e.g. "#7 Background+Ribbon 200x100 at 10,20"
+    pub fn label(&self) -> String {
+        let widgets = if self.widgets.is_empty() {
+            "Slot".to_string()
+        } else {
+            self.widgets.join("+")
+        };
+        format!(
+            "#{} {} {}x{} at {},{}",
+            self.id, widgets, self.size.0, self.size.1, self.offset.0, self.offset.1
+        )
+    }
+
+    fn write_text(&self, depth: usize, text: &mut String) {
+        let _ = write!(text, "{:indent$}{}", "", self.label(), indent = depth * 2);
+        if let Some((width, height)) = self.desired_size {
+            let _ = write!(text, " desired {}x{}", width, height);
+        }
+        if self.scale != (1., 1.) {
+            let _ = write!(text, " scale {}x{}", self.scale.0, self.scale.1);
+        }
+        for (flag, name) in [
+            (!self.visible, "hidden"),
+            (self.clip_to_bounds, "clip"),
+            (self.focusable, "focusable"),
+            (self.focused, "focused"),
+        ] {
+            if flag {
+                let _ = write!(text, " {}", name);
+            }
+        }
+        text.push('\n');
+        for child in &self.children {
+            child.write_text(depth + 1, text);
+        }
+    }
+
+    fn collect_outlines(
+        &self,
+        origin: (f32, f32),
+        scale: (f32, f32),
+        outlines: &mut Vec<(Rect, String)>,
+    ) {
+        if !self.visible {
+            return;
+        }
+        let origin = (
+            origin.0 + self.offset.0 * scale.0,
+            origin.1 + self.offset.1 * scale.1,
+        );
+        let scale = (scale.0 * self.scale.0, scale.1 * self.scale.1);
+        outlines.push((
+            Rect::new(
+                origin.0,
+                origin.1,
+                self.size.0 * scale.0,
+                self.size.1 * scale.1,
+            ),
+            self.label(),
+        ));
+        for child in &self.children {
+            child.collect_outlines(origin, scale, outlines);
+        }
+    }
+}
+
+/// Geometry and state of the whole slot tree of the frame
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FrameInfo {
+    pub size: (f32, f32),
+    /// Index of the first root slot which gets input, see `FrameTag::open_modal_slot`
+    pub input_layer: usize,
+    /// Root slots from the bottom one up
+    pub slots: Vec<SlotInfo>,
+}
+
+impl FrameInfo {
+    /// Indented tree, one slot per line
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "frame {}x{} input layer {}\n",
+            self.size.0, self.size.1, self.input_layer
+        );
+        for slot in &self.slots {
+            slot.write_text(0, &mut text);
+        }
+        text
+    }
+
+    pub fn json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("frame info is always serializable")
+    }
+
+    /// Rectangles of the visible slots in frame coordinates with their labels, parents first
+    pub fn outlines(&self) -> Vec<(Rect, String)> {
+        let mut outlines = Vec::new();
+        for slot in &self.slots {
+            slot.collect_outlines((0., 0.), (1., 1.), &mut outlines);
+        }
+        outlines
+    }
+}
+
+/// Overlay above all slots of the frame outlining every visible slot with its label.
+/// It doesn't get input and is refreshed periodically while enabled
+pub struct Inspector {
+    frame: FrameTag,
+    visual: SpriteVisual,
+    enabled: bool,
+    tasks: TaskGroup,
+}
+
+impl Inspector {
+    fn new(frame: FrameTag) -> crate::Result<Self> {
+        let visual = frame.compositor()?.CreateSpriteVisual()?;
+        let tasks = TaskGroup::new(frame.clone());
+        Ok(Self {
+            frame,
+            visual,
+            enabled: false,
+            tasks,
+        })
+    }
+
+    fn set_enabled(&mut self, enabled: bool) -> crate::Result<()> {
+        if self.enabled == enabled {
+            return Ok(());
+        }
+        tracing::debug!(enabled, "inspector");
+        let children = self.frame.frame_visual()?.Children()?;
+        if enabled {
+            children.InsertAtTop(self.visual.clone())?;
+        } else {
+            children.Remove(&self.visual)?;
+        }
+        self.enabled = enabled;
+        self.redraw()
+    }
+
+    fn redraw(&self) -> crate::Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        // Slots opened after the overlay are put above it
+        let children = self.frame.frame_visual()?.Children()?;
+        children.Remove(&self.visual)?;
+        children.InsertAtTop(self.visual.clone())?;
+        let info = self.frame.inspect()?;
+        let size = Vector2 {
+            X: info.size.0,
+            Y: info.size.1,
+        };
+        let surface = self.frame.graphics_device()?.CreateDrawingSurface(
+            Size {
+                Width: size.X.max(1.),
+                Height: size.Y.max(1.),
+            },
+            DirectXPixelFormat::B8G8R8A8UIntNormalized,
+            DirectXAlphaMode::Premultiplied,
+        )?;
+        let color = self.frame.theme()?.resolve(&"inspector".into());
+        let format = CanvasTextFormat::new()?;
+        format.SetFontSize(LABEL_FONT_SIZE)?;
+        let session = CanvasComposition::CreateDrawingSession(&surface)?;
+        session.Clear(Colors::Transparent()?)?;
+        for (rect, label) in info.outlines() {
+            session.DrawRectangleAtCoordsWithColor(
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+                color,
+            )?;
+            session.DrawTextAtPointCoordsWithColorAndFormat(
+                label.as_str(),
+                rect.x + 2.,
+                rect.y + 1.,
+                color,
+                &format,
+            )?;
+        }
+        session.Close()?;
+        let brush = self
+            .frame
+            .compositor()?
+            .CreateSurfaceBrushWithSurface(&surface)?;
+        self.visual.SetBrush(brush)?;
+        self.visual.SetSize(size)?;
+        Ok(())
+    }
+}
+
+impl Drop for Inspector {
+    fn drop(&mut self) {
+        if let (true, Ok(frame_visual)) = (self.enabled, self.frame.frame_visual()) {
+            let _ = frame_visual.Children().map(|c| c.Remove(&self.visual));
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct InspectorKeeper(Keeper<Inspector>);
+
+impl InspectorKeeper {
+    /// The overlay is disabled initially
+    pub fn new(frame: FrameTag) -> crate::Result<Self> {
+        let keeper = Self(Keeper::new(Inspector::new(frame)?));
+        keeper.spawn_event_handlers()?;
+        Ok(keeper)
+    }
+    pub fn tag(&self) -> InspectorTag {
+        InspectorTag(self.0.tag())
+    }
+    pub fn get(&self) -> RwLockReadGuard<'_, Inspector> {
+        self.0.get()
+    }
+    pub fn get_mut(&self) -> RwLockWriteGuard<'_, Inspector> {
+        self.0.get_mut()
+    }
+    /// Resolves when event handlers of the inspector are stopped, i.e. after all keepers are dropped
+    pub fn tasks_finished(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.get().tasks.finished()
+    }
+    fn spawn_event_handlers(&self) -> crate::Result<()> {
+        let tag = self.tag();
+        let mut ticks = self.get().frame.interval(REFRESH_PERIOD)?;
+        self.get_mut()
+            .tasks
+            .spawn("Inspector::on_interval", async move {
+                while ticks.next().await.is_some() {
+                    tag.redraw()?;
+                }
+                Ok(())
+            })
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct InspectorTag(Tag<Inspector>);
+
+impl InspectorTag {
+    pub fn is_enabled(&self) -> crate::Result<bool> {
+        Ok(self.0.call(|v| v.enabled)?)
+    }
+    pub fn set_enabled(&self, enabled: bool) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_enabled(enabled))??)
+    }
+    /// Returns true if the overlay is enabled now
+    pub fn toggle(&self) -> crate::Result<bool> {
+        Ok(self.0.call_mut(|v| {
+            let enabled = !v.enabled;
+            v.set_enabled(enabled).map(|_| enabled)
+        })??)
+    }
+    /// Redraws the overlay now instead of on the next refresh
+    pub fn redraw(&self) -> crate::Result<()> {
+        Ok(self.0.call(|v| v.redraw())??)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameInfo, SlotInfo};
+    use crate::layout::Rect;
+
+    fn slot(id: usize, offset: (f32, f32), size: (f32, f32)) -> SlotInfo {
+        SlotInfo {
+            id,
+            widgets: Vec::new(),
+            offset,
+            size,
+            scale: (1., 1.),
+            desired_size: None,
+            visible: true,
+            clip_to_bounds: false,
+            focusable: false,
+            focused: false,
+            children: Vec::new(),
+        }
+    }
+
+    /// Frame with a scaled background holding a focused child and a hidden one
+    fn frame() -> FrameInfo {
+        let mut child = slot(2, (5., 5.), (10., 10.));
+        child.desired_size = Some((10., 8.));
+        child.focusable = true;
+        child.focused = true;
+        let mut hidden = slot(3, (0., 0.), (1., 1.));
+        hidden.visible = false;
+        let mut parent = slot(1, (200., 20.), (100., 50.));
+        parent.widgets = vec!["Background".to_string(), "Ribbon".to_string()];
+        parent.scale = (0.5, 0.5);
+        parent.clip_to_bounds = true;
+        parent.children = vec![child, hidden];
+        FrameInfo {
+            size: (400., 300.),
+            input_layer: 0,
+            slots: vec![parent],
+        }
+    }
+
+    #[test]
+    fn text_is_indented_tree() {
+        assert_eq!(
+            frame().text(),
+            concat!(
+                "frame 400x300 input layer 0\n",
+                "#1 Background+Ribbon 100x50 at 200,20 scale 0.5x0.5 clip\n",
+                "  #2 Slot 10x10 at 5,5 desired 10x8 focusable focused\n",
+                "  #3 Slot 1x1 at 0,0 hidden\n",
+            )
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let info = frame();
+        let parsed: FrameInfo = serde_json::from_str(&info.json()).unwrap();
+        assert_eq!(parsed, info);
+    }
+
+    #[test]
+    fn outlines_accumulate_offset_and_scale() {
+        let outlines = frame().outlines();
+        let rects = outlines.iter().map(|(rect, _)| *rect).collect::<Vec<_>>();
+        // The hidden slot is left out
+        assert_eq!(
+            rects,
+            vec![
+                Rect::new(200., 20., 50., 25.),
+                Rect::new(202.5, 22.5, 5., 5.),
+            ]
+        );
+        assert_eq!(outlines[1].1, "#2 Slot 10x10 at 5,5");
+    }
+}
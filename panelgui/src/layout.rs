@@ -0,0 +1,640 @@
+//! Layout computations. They are pure functions independent of the composition backend
+
+use serde::Deserialize;
+
+/// Limits of the cell along the ribbon's axis
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LinearLimit {
+    /// Share of the free space. Cells with zero (or negative) ratio are fixed: they get `min_size`
+    pub ratio: f32,
+    pub min_size: f32,
+    pub max_size: Option<f32>,
+}
+
+impl LinearLimit {
+    pub fn new(ratio: f32, min_size: f32, max_size: Option<f32>) -> Self {
+        Self {
+            ratio,
+            min_size,
+            max_size,
+        }
+    }
+    pub fn fixed(size: f32) -> Self {
+        Self::new(0., size, Some(size))
+    }
+}
+
+impl Default for LinearLimit {
+    fn default() -> Self {
+        Self::new(1., 0., None)
+    }
+}
+
+/// What to do when the minimal sizes of the cells don't fit into the target size
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+pub enum Overflow {
+    /// Shrink all cells proportionally to their minimal sizes, so that they fill the target exactly
+    Shrink,
+    /// Keep the minimal sizes, the cells at the end go out of the target
+    #[default]
+    Clip,
+    /// Fail with `Error::LayoutOverflow`
+    Report,
+}
+
+fn sanitize(value: f32) -> f32 {
+    if value.is_finite() && value > 0. {
+        value
+    } else {
+        0.
+    }
+}
+
+/// Distributes `target` size between cells according to their ratios and limits.
+///
+/// The free space is shared by ratio between flexible cells; a cell which would get less
+/// than `min_size` or more than `max_size` is frozen at that limit and the rest is shared
+/// again between the others. Negative or NaN values are treated as zero, `max_size`
+/// smaller than `min_size` is ignored. If all cells reach their maximums, sizes sum to less
+/// than the target. If the minimal sizes sum to more than the target, `overflow` applies.
+pub fn solve(limits: &[LinearLimit], target: f32, overflow: Overflow) -> crate::Result<Vec<f32>> {
+    let target = sanitize(target);
+    let ratios = limits.iter().map(|l| sanitize(l.ratio)).collect::<Vec<_>>();
+    let mins = limits
+        .iter()
+        .map(|l| sanitize(l.min_size))
+        .collect::<Vec<_>>();
+    let maxs = limits
+        .iter()
+        .zip(&mins)
+        .map(|(l, &min)| {
+            l.max_size
+                .filter(|max| !max.is_nan())
+                .map_or(f32::INFINITY, |max| max.max(min))
+        })
+        .collect::<Vec<_>>();
+
+    let sum_min = mins.iter().sum::<f32>();
+    if sum_min > target {
+        return match overflow {
+            Overflow::Shrink => {
+                let scale = target / sum_min;
+                Ok(mins.iter().map(|min| min * scale).collect())
+            }
+            Overflow::Clip => Ok(mins),
+            Overflow::Report => Err(crate::Error::LayoutOverflow {
+                required: sum_min,
+                available: target,
+            }),
+        };
+    }
+
+    let mut sizes = mins.clone();
+    let mut frozen = ratios
+        .iter()
+        .zip(mins.iter().zip(&maxs))
+        .map(|(&ratio, (min, max))| ratio == 0. || min == max)
+        .collect::<Vec<_>>();
+    // Each pass freezes at least one cell, so the loop ends in at most `limits.len()` passes
+    loop {
+        let sum_ratio = (0..sizes.len())
+            .filter(|&i| !frozen[i])
+            .map(|i| ratios[i])
+            .sum::<f32>();
+        if sum_ratio == 0. {
+            break;
+        }
+        let used = (0..sizes.len())
+            .filter(|&i| frozen[i])
+            .map(|i| sizes[i])
+            .sum::<f32>();
+        let free = sanitize(target - used);
+        let mut violation = 0.;
+        let mut clamped = Vec::new();
+        for i in (0..sizes.len()).filter(|&i| !frozen[i]) {
+            let share = free * ratios[i] / sum_ratio;
+            sizes[i] = share.max(mins[i]).min(maxs[i]);
+            if sizes[i] != share {
+                violation += sizes[i] - share;
+                clamped.push(i);
+            }
+        }
+        if clamped.is_empty() {
+            break;
+        }
+        // Freeze the cells which limit the others most: if the minimums took extra space,
+        // freeze them; if the maximums released some space, freeze them
+        for i in clamped {
+            let at_min = sizes[i] == mins[i];
+            if violation == 0. || (violation > 0.) == at_min {
+                frozen[i] = true;
+            }
+        }
+    }
+    Ok(sizes)
+}
+
+/// Sizes of the four sides of a box: used for cell margins and ribbon padding
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Thickness {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Thickness {
+    pub fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+    pub fn uniform(value: f32) -> Self {
+        Self::new(value, value, value, value)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+impl Axis {
+    fn main(&self, width: f32, height: f32) -> f32 {
+        match self {
+            Axis::Horizontal => width,
+            Axis::Vertical => height,
+        }
+    }
+    fn cross(&self, width: f32, height: f32) -> f32 {
+        match self {
+            Axis::Horizontal => height,
+            Axis::Vertical => width,
+        }
+    }
+    /// Sides of the thickness as (main start, main end, cross start, cross end)
+    fn sides(&self, t: Thickness) -> (f32, f32, f32, f32) {
+        match self {
+            Axis::Horizontal => (t.left, t.right, t.top, t.bottom),
+            Axis::Vertical => (t.top, t.bottom, t.left, t.right),
+        }
+    }
+    fn rect(&self, main_pos: f32, cross_pos: f32, main_size: f32, cross_size: f32) -> Rect {
+        match self {
+            Axis::Horizontal => Rect::new(main_pos, cross_pos, main_size, cross_size),
+            Axis::Vertical => Rect::new(cross_pos, main_pos, cross_size, main_size),
+        }
+    }
+}
+
+/// Position of the cell's content inside the space given to the cell
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+pub enum Alignment {
+    Start,
+    Center,
+    End,
+    /// Take the whole space on the cross axis. Content which can't fill the space because
+    /// of aspect ratio lock is centered
+    #[default]
+    Stretch,
+}
+
+/// Shrinks one of the sizes so that width / height == aspect_ratio
+pub fn fit_aspect(width: f32, height: f32, aspect_ratio: Option<f32>) -> (f32, f32) {
+    match aspect_ratio.filter(|a| a.is_finite() && *a > 0.) {
+        Some(a) if width > height * a => (height * a, height),
+        Some(a) => (width, width / a),
+        None => (width, height),
+    }
+}
+
+/// Positions content of the given size inside the `outer` box on both axes
+pub fn place(outer: Rect, width: f32, height: f32, align: Alignment) -> Rect {
+    let offset = |free: f32| match align {
+        Alignment::Start => 0.,
+        Alignment::End => free,
+        Alignment::Center | Alignment::Stretch => free / 2.,
+    };
+    Rect::new(
+        outer.x + offset(outer.width - width),
+        outer.y + offset(outer.height - height),
+        width,
+        height,
+    )
+}
+
+/// Cell of the linear layout. The margin is outside of the limits: `min_size` and
+/// `max_size` restrict the cell itself. Unless the cell is stretched, its size on the cross
+/// axis is the corresponding part of `content_ratio` of the available space.
+/// If `desired_size` (width, height) is set, the cell is sized by it instead: the ratio and
+/// `content_ratio` are ignored, only the limits apply.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LinearItem {
+    pub limit: LinearLimit,
+    pub margin: Thickness,
+    pub content_ratio: (f32, f32),
+    pub align: Alignment,
+    pub aspect_ratio: Option<f32>,
+    pub desired_size: Option<(f32, f32)>,
+}
+
+impl LinearItem {
+    /// Limit for the solver: cells with desired size are fixed
+    fn solver_limit(&self, axis: Axis) -> LinearLimit {
+        match self.desired_size {
+            Some((width, height)) => {
+                let limit = self.limit;
+                let size = sanitize(axis.main(width, height)).max(limit.min_size);
+                LinearLimit::fixed(limit.max_size.map_or(size, |max| size.min(max)))
+            }
+            None => self.limit,
+        }
+    }
+}
+
+impl Default for LinearItem {
+    fn default() -> Self {
+        Self {
+            limit: LinearLimit::default(),
+            margin: Thickness::default(),
+            content_ratio: (1., 1.),
+            align: Alignment::default(),
+            aspect_ratio: None,
+            desired_size: None,
+        }
+    }
+}
+
+/// Places cells one after another along the `axis` inside the box of the given size.
+/// Padding, spacing between cells and cell margins are taken out of the space
+/// before it's distributed with `solve`. Then the content of each cell is sized and
+/// aligned inside the space given to it.
+pub fn arrange(
+    axis: Axis,
+    width: f32,
+    height: f32,
+    padding: Thickness,
+    spacing: f32,
+    items: &[LinearItem],
+    overflow: Overflow,
+) -> crate::Result<Vec<Rect>> {
+    let (pad_start, pad_end, pad_cross_start, pad_cross_end) = axis.sides(padding);
+    let spacing = sanitize(spacing);
+    let gaps = spacing * items.len().saturating_sub(1) as f32;
+    let margins = items
+        .iter()
+        .map(|item| {
+            let (start, end, _, _) = axis.sides(item.margin);
+            start + end
+        })
+        .sum::<f32>();
+    let available = axis.main(width, height) - pad_start - pad_end - gaps - margins;
+    let limits = items
+        .iter()
+        .map(|item| item.solver_limit(axis))
+        .collect::<Vec<_>>();
+    let sizes = solve(&limits, available, overflow)?;
+
+    let cross = axis.cross(width, height);
+    let mut pos = pad_start;
+    let mut rects = Vec::with_capacity(items.len());
+    for (item, size) in items.iter().zip(sizes) {
+        let (margin_start, margin_end, margin_cross_start, margin_cross_end) =
+            axis.sides(item.margin);
+        pos += margin_start;
+        let mut cross_size = sanitize(
+            cross - pad_cross_start - pad_cross_end - margin_cross_start - margin_cross_end,
+        );
+        let outer = axis.rect(pos, pad_cross_start + margin_cross_start, size, cross_size);
+        if item.align != Alignment::Stretch {
+            cross_size = match item.desired_size {
+                Some((width, height)) => sanitize(axis.cross(width, height)).min(cross_size),
+                None => {
+                    let (ratio_x, ratio_y) = item.content_ratio;
+                    cross_size * sanitize(axis.cross(ratio_x, ratio_y))
+                }
+            };
+        }
+        let content = axis.rect(0., 0., size, cross_size);
+        let (content_width, content_height) =
+            fit_aspect(content.width, content.height, item.aspect_ratio);
+        rects.push(place(outer, content_width, content_height, item.align));
+        pos += size + margin_end + spacing;
+    }
+    Ok(rects)
+}
+
+/// Size (width, height) the linear layout wants to have: cells get their desired
+/// or minimal sizes
+pub fn measure(axis: Axis, padding: Thickness, spacing: f32, items: &[LinearItem]) -> (f32, f32) {
+    let (pad_start, pad_end, pad_cross_start, pad_cross_end) = axis.sides(padding);
+    let gaps = sanitize(spacing) * items.len().saturating_sub(1) as f32;
+    let mut main = pad_start + pad_end + gaps;
+    let mut cross = 0.;
+    for item in items {
+        let (margin_start, margin_end, margin_cross_start, margin_cross_end) =
+            axis.sides(item.margin);
+        main += margin_start + item.solver_limit(axis).min_size + margin_end;
+        let content_cross = item
+            .desired_size
+            .map_or(0., |(width, height)| sanitize(axis.cross(width, height)));
+        cross = f32::max(cross, margin_cross_start + content_cross + margin_cross_end);
+    }
+    let rect = axis.rect(0., 0., main, pad_cross_start + cross + pad_cross_end);
+    (rect.width, rect.height)
+}
+
+/// Cell of the stack layout. Cells overlap each other, each takes `content_ratio` part
+/// of the box (without padding and margin), or its `desired_size` if set.
+/// `Stretch` alignment centers the content
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StackItem {
+    pub margin: Thickness,
+    pub content_ratio: (f32, f32),
+    pub align: Alignment,
+    pub aspect_ratio: Option<f32>,
+    pub desired_size: Option<(f32, f32)>,
+}
+
+impl Default for StackItem {
+    fn default() -> Self {
+        Self {
+            margin: Thickness::default(),
+            content_ratio: (1., 1.),
+            align: Alignment::default(),
+            aspect_ratio: None,
+            desired_size: None,
+        }
+    }
+}
+
+pub fn stack(width: f32, height: f32, padding: Thickness, item: &StackItem) -> Rect {
+    let margin = item.margin;
+    let x = padding.left + margin.left;
+    let y = padding.top + margin.top;
+    let outer = Rect::new(
+        x,
+        y,
+        sanitize(width - x - padding.right - margin.right),
+        sanitize(height - y - padding.bottom - margin.bottom),
+    );
+    let (width, height) = match item.desired_size {
+        Some((width, height)) => (
+            sanitize(width).min(outer.width),
+            sanitize(height).min(outer.height),
+        ),
+        None => (
+            outer.width * sanitize(item.content_ratio.0),
+            outer.height * sanitize(item.content_ratio.1),
+        ),
+    };
+    let (content_width, content_height) = fit_aspect(width, height, item.aspect_ratio);
+    place(outer, content_width, content_height, item.align)
+}
+
+/// Size (width, height) the stack layout wants to have: the largest desired size of the cells
+pub fn measure_stack(padding: Thickness, items: &[StackItem]) -> (f32, f32) {
+    let mut width = 0.;
+    let mut height = 0.;
+    for item in items {
+        let (desired_width, desired_height) = item
+            .desired_size
+            .map_or((0., 0.), |(w, h)| (sanitize(w), sanitize(h)));
+        width = f32::max(width, item.margin.left + desired_width + item.margin.right);
+        height = f32::max(
+            height,
+            item.margin.top + desired_height + item.margin.bottom,
+        );
+    }
+    (
+        padding.left + width + padding.right,
+        padding.top + height + padding.bottom,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{solve, LinearLimit, Overflow};
+
+    const EPSILON: f32 = 1e-3;
+
+    fn assert_sizes(actual: Vec<f32>, expected: &[f32]) {
+        assert_eq!(
+            actual.len(),
+            expected.len(),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < EPSILON, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn empty() {
+        assert_sizes(solve(&[], 100., Overflow::Report).unwrap(), &[]);
+    }
+
+    #[test]
+    fn shares_by_ratio() {
+        let limits = [
+            LinearLimit::new(1., 0., None),
+            LinearLimit::new(3., 0., None),
+        ];
+        assert_sizes(solve(&limits, 100., Overflow::Clip).unwrap(), &[25., 75.]);
+    }
+
+    #[test]
+    fn fixed_and_zero_ratio_cells_get_min_size() {
+        let limits = [
+            LinearLimit::fixed(30.),
+            LinearLimit::new(0., 10., None),
+            LinearLimit::default(),
+        ];
+        assert_sizes(
+            solve(&limits, 100., Overflow::Clip).unwrap(),
+            &[30., 10., 60.],
+        );
+    }
+
+    #[test]
+    fn respects_min_size() {
+        let limits = [LinearLimit::new(1., 60., None), LinearLimit::default()];
+        assert_sizes(solve(&limits, 100., Overflow::Clip).unwrap(), &[60., 40.]);
+    }
+
+    #[test]
+    fn respects_max_size() {
+        let limits = [LinearLimit::new(1., 0., Some(10.)), LinearLimit::default()];
+        assert_sizes(solve(&limits, 100., Overflow::Clip).unwrap(), &[10., 90.]);
+    }
+
+    #[test]
+    fn all_at_max_size_leave_space() {
+        let limits = [LinearLimit::new(1., 0., Some(10.)); 2];
+        assert_sizes(solve(&limits, 100., Overflow::Clip).unwrap(), &[10., 10.]);
+    }
+
+    #[test]
+    fn max_below_min_is_ignored() {
+        let limits = [LinearLimit::new(1., 20., Some(5.)), LinearLimit::default()];
+        assert_sizes(solve(&limits, 100., Overflow::Clip).unwrap(), &[20., 80.]);
+    }
+
+    #[test]
+    fn overflow_shrink() {
+        let limits = [
+            LinearLimit::new(1., 60., None),
+            LinearLimit::new(1., 20., None),
+        ];
+        assert_sizes(solve(&limits, 40., Overflow::Shrink).unwrap(), &[30., 10.]);
+    }
+
+    #[test]
+    fn overflow_clip() {
+        let limits = [
+            LinearLimit::new(1., 60., None),
+            LinearLimit::new(1., 20., None),
+        ];
+        assert_sizes(solve(&limits, 40., Overflow::Clip).unwrap(), &[60., 20.]);
+    }
+
+    #[test]
+    fn overflow_report() {
+        let limits = [
+            LinearLimit::new(1., 60., None),
+            LinearLimit::new(1., 20., None),
+        ];
+        match solve(&limits, 40., Overflow::Report) {
+            Err(crate::Error::LayoutOverflow {
+                required,
+                available,
+            }) => {
+                assert_eq!(required, 80.);
+                assert_eq!(available, 40.);
+            }
+            other => panic!("expected overflow, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn nan_and_negative_values_are_zero() {
+        let limits = [
+            LinearLimit::new(f32::NAN, -10., None),
+            LinearLimit::new(-1., f32::NAN, Some(f32::NAN)),
+            LinearLimit::new(1., -5., None),
+        ];
+        assert_sizes(
+            solve(&limits, 100., Overflow::Report).unwrap(),
+            &[0., 0., 100.],
+        );
+        assert_sizes(
+            solve(&limits, f32::NAN, Overflow::Report).unwrap(),
+            &[0., 0., 0.],
+        );
+        assert_sizes(
+            solve(&limits, -100., Overflow::Report).unwrap(),
+            &[0., 0., 0.],
+        );
+    }
+
+    fn value() -> impl Strategy<Value = f32> {
+        prop_oneof![
+            8 => -10f32..200.,
+            1 => Just(f32::NAN),
+            1 => Just(0.),
+        ]
+    }
+
+    fn limit() -> impl Strategy<Value = LinearLimit> {
+        (value(), value(), proptest::option::of(value())).prop_map(|(ratio, min_size, max_size)| {
+            LinearLimit::new(ratio / 20., min_size, max_size)
+        })
+    }
+
+    fn sanitize(value: f32) -> f32 {
+        if value > 0. && value.is_finite() {
+            value
+        } else {
+            0.
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn sizes_fit_limits_and_target(
+            limits in proptest::collection::vec(limit(), 0..8),
+            target in value(),
+        ) {
+            let target = sanitize(target);
+            let mins = limits.iter().map(|l| sanitize(l.min_size)).collect::<Vec<_>>();
+            let sum_min = mins.iter().sum::<f32>();
+            let sizes = solve(&limits, target, Overflow::Report);
+            if sum_min > target {
+                prop_assert!(sizes.is_err());
+                return Ok(());
+            }
+            let sizes = sizes.unwrap();
+            prop_assert_eq!(sizes.len(), limits.len());
+            let tolerance = EPSILON * target.max(1.);
+            for ((size, limit), min) in sizes.iter().zip(&limits).zip(&mins) {
+                prop_assert!(size.is_finite());
+                prop_assert!(*size >= min - tolerance);
+                if let Some(max) = limit.max_size.filter(|max| !max.is_nan()) {
+                    prop_assert!(*size <= max.max(*min) + tolerance);
+                }
+                if sanitize(limit.ratio) == 0. {
+                    prop_assert!((size - min).abs() <= tolerance);
+                }
+            }
+            let total = sizes.iter().sum::<f32>();
+            prop_assert!(total <= target + tolerance);
+            // An unlimited flexible cell takes all the space left
+            if limits.iter().any(|l| sanitize(l.ratio) > 0. && l.max_size.is_none()) {
+                prop_assert!((total - target).abs() <= tolerance);
+            }
+        }
+
+        #[test]
+        fn shrink_fills_target_exactly(
+            limits in proptest::collection::vec(limit(), 1..8),
+            target in value(),
+        ) {
+            let target = sanitize(target);
+            let sum_min = limits.iter().map(|l| sanitize(l.min_size)).sum::<f32>();
+            prop_assume!(sum_min > target);
+            let sizes = solve(&limits, target, Overflow::Shrink).unwrap();
+            let total = sizes.iter().sum::<f32>();
+            prop_assert!((total - target).abs() <= EPSILON * target.max(1.));
+            let clipped = solve(&limits, target, Overflow::Clip).unwrap();
+            prop_assert!((clipped.iter().sum::<f32>() - sum_min).abs() <= EPSILON * sum_min);
+        }
+    }
+}
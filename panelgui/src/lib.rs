@@ -1,18 +1,47 @@
+pub mod animation;
+mod animator;
 mod background;
+mod bitmap;
+mod clock;
+mod dialog;
+mod executor;
 mod frame;
+mod image;
+mod inspector;
+pub mod layout;
+pub mod path;
 mod ribbon;
+mod shape;
 mod slot;
-// mod text;
 mod slot_event;
+mod task_group;
+mod text;
+mod theme;
+pub mod theme_file;
+pub mod tree;
 
-pub use background::{Background, BackgroundKeeper, BackgroundTag};
-pub use frame::{Frame, FrameKeeper, FrameTag};
+pub use animator::{AnimationTarget, AnimationValue, Animator};
+pub use background::{
+    Background, BackgroundKeeper, BackgroundTag, Border, Gradient, NineGrid, Shadow,
+};
+pub use bitmap::Bitmap;
+pub use clock::{Clock, ClockMode, Tick};
+pub use dialog::{Dialog, DialogHandle};
+pub use executor::{Executor, ManualExecutor};
+pub use frame::{Frame, FrameKeeper, FrameTag, TaskError};
+// `self` tells the module from the `image` crate
+pub use self::image::{Image, ImageFit, ImageKeeper, ImageTag};
+pub use inspector::{FrameInfo, Inspector, InspectorKeeper, InspectorTag, SlotInfo};
 pub use ribbon::{CellLimit, Ribbon, RibbonKeeper, RibbonOrientation, RibbonTag};
-pub use slot::{Slot, SlotKeeper, SlotTag};
-// pub use text::{Text, TextKeeper, TextTag};
+pub use shape::{Shape, ShapeKeeper, ShapeTag, Stroke};
+pub use slot::{Hit, Slot, SlotKeeper, SlotPlug, SlotTag};
 pub use slot_event::{
-    MouseLeftPressed, MouseLeftPressedFocused, ReceiveSlotEvent, SendSlotEvent, SlotSize,
+    FocusChanged, Key, KeyPressed, MouseLeftPressed, MouseLeftPressedFocused, ReceiveSlotEvent,
+    SendSlotEvent, SlotFocused, SlotSize, ThemeChanged,
 };
+pub use task_group::TaskGroup;
+pub use text::{Text, TextKeeper, TextTag};
+pub use theme::{parse_color, Font, Theme, ThemeColor, TileStyle};
 
 use futures::task::SpawnError;
 // pub use ribbon::{Ribbon, RibbonKeeper, RibbonTag};
@@ -22,12 +51,30 @@ use thiserror::Error;
 pub enum Error {
     #[error("Bad element index")]
     BadIndex,
+    #[error("Layout overflow: cells require {required} but only {available} is available")]
+    LayoutOverflow { required: f32, available: f32 },
+    #[error("Bad widget tree description: {0}")]
+    Description(String),
+    #[error("{origin}:{line}:{column}: {message}")]
+    ThemeFile {
+        origin: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("{path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
     #[error(transparent)]
     Spawn(SpawnError),
     #[error(transparent)]
     AsyncObject(async_object::Error),
     #[error(transparent)]
     Windows(windows::Error),
+    #[error(transparent)]
+    Image(::image::ImageError),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -48,3 +95,8 @@ impl From<SpawnError> for Error {
         Error::Spawn(e)
     }
 }
+impl From<::image::ImageError> for Error {
+    fn from(e: ::image::ImageError) -> Self {
+        Error::Image(e)
+    }
+}
@@ -0,0 +1,443 @@
+//! Vector paths independent of the composition backend. Coordinates are in units of the path:
+//! the widget showing it scales the path to its slot
+
+use std::f32::consts::PI;
+
+use serde::Deserialize;
+
+use crate::layout::Rect;
+
+type Point = (f32, f32);
+
+/// Number of line segments a curve is split into by `Path::flatten`
+const CURVE_SEGMENTS: usize = 16;
+
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+pub enum PathCommand {
+    /// Starts a new figure
+    MoveTo(Point),
+    LineTo(Point),
+    QuadTo {
+        control: Point,
+        to: Point,
+    },
+    CubicTo {
+        control1: Point,
+        control2: Point,
+        to: Point,
+    },
+    /// Part of the ellipse with `radius` rotated by `rotation` degrees, as in SVG.
+    /// Of the two ellipses and four arcs going to `to` the flags choose one
+    ArcTo {
+        radius: Point,
+        #[serde(default)]
+        rotation: f32,
+        #[serde(default)]
+        large_arc: bool,
+        #[serde(default)]
+        clockwise: bool,
+        to: Point,
+    },
+    /// Closes the figure with a line to its start
+    Close,
+    /// Whole closed figure
+    Ellipse {
+        center: Point,
+        radius: Point,
+    },
+    /// Whole closed figure
+    RoundedRect {
+        origin: Point,
+        size: Point,
+        radius: f32,
+    },
+}
+
+/// Figures made of lines and curves
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Path {
+    commands: Vec<PathCommand>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ellipse(center: Point, radius: Point) -> Self {
+        Self::new().push(PathCommand::Ellipse { center, radius })
+    }
+
+    pub fn rounded_rect(origin: Point, size: Point, radius: f32) -> Self {
+        Self::new().push(PathCommand::RoundedRect {
+            origin,
+            size,
+            radius,
+        })
+    }
+
+    pub fn push(mut self, command: PathCommand) -> Self {
+        self.commands.push(command);
+        self
+    }
+
+    pub fn move_to(self, to: Point) -> Self {
+        self.push(PathCommand::MoveTo(to))
+    }
+
+    pub fn line_to(self, to: Point) -> Self {
+        self.push(PathCommand::LineTo(to))
+    }
+
+    pub fn quad_to(self, control: Point, to: Point) -> Self {
+        self.push(PathCommand::QuadTo { control, to })
+    }
+
+    pub fn cubic_to(self, control1: Point, control2: Point, to: Point) -> Self {
+        self.push(PathCommand::CubicTo {
+            control1,
+            control2,
+            to,
+        })
+    }
+
+    pub fn arc_to(
+        self,
+        radius: Point,
+        rotation: f32,
+        large_arc: bool,
+        clockwise: bool,
+        to: Point,
+    ) -> Self {
+        self.push(PathCommand::ArcTo {
+            radius,
+            rotation,
+            large_arc,
+            clockwise,
+            to,
+        })
+    }
+
+    pub fn close(self) -> Self {
+        self.push(PathCommand::Close)
+    }
+
+    pub fn commands(&self) -> &[PathCommand] {
+        &self.commands
+    }
+
+    /// Commands with ellipses and rounded rectangles replaced by lines and arcs.
+    /// Each figure starts with `MoveTo`: a figure without it starts where the previous one ended
+    pub fn primitives(&self) -> Vec<PathCommand> {
+        let mut primitives = Vec::new();
+        // Start of the current figure and the current point
+        let mut start = (0., 0.);
+        let mut current = (0., 0.);
+        let mut open = false;
+        for &command in &self.commands {
+            match command {
+                PathCommand::MoveTo(to) => {
+                    primitives.push(command);
+                    start = to;
+                    current = to;
+                    open = true;
+                }
+                PathCommand::LineTo(to)
+                | PathCommand::QuadTo { to, .. }
+                | PathCommand::CubicTo { to, .. }
+                | PathCommand::ArcTo { to, .. } => {
+                    if !open {
+                        primitives.push(PathCommand::MoveTo(current));
+                        start = current;
+                        open = true;
+                    }
+                    primitives.push(command);
+                    current = to;
+                }
+                PathCommand::Close => {
+                    if open {
+                        primitives.push(command);
+                        current = start;
+                        open = false;
+                    }
+                }
+                PathCommand::Ellipse { center, radius } => {
+                    let right = (center.0 + radius.0, center.1);
+                    let left = (center.0 - radius.0, center.1);
+                    let half = |to| PathCommand::ArcTo {
+                        radius,
+                        rotation: 0.,
+                        large_arc: false,
+                        clockwise: true,
+                        to,
+                    };
+                    primitives.extend_from_slice(&[
+                        PathCommand::MoveTo(right),
+                        half(left),
+                        half(right),
+                        PathCommand::Close,
+                    ]);
+                    current = right;
+                    open = false;
+                }
+                PathCommand::RoundedRect {
+                    origin: (x, y),
+                    size: (width, height),
+                    radius,
+                } => {
+                    let r = radius.min(width / 2.).min(height / 2.).max(0.);
+                    let corner = |to| PathCommand::ArcTo {
+                        radius: (r, r),
+                        rotation: 0.,
+                        large_arc: false,
+                        clockwise: true,
+                        to,
+                    };
+                    primitives.push(PathCommand::MoveTo((x + r, y)));
+                    primitives.push(PathCommand::LineTo((x + width - r, y)));
+                    if r > 0. {
+                        primitives.push(corner((x + width, y + r)));
+                    }
+                    primitives.push(PathCommand::LineTo((x + width, y + height - r)));
+                    if r > 0. {
+                        primitives.push(corner((x + width - r, y + height)));
+                    }
+                    primitives.push(PathCommand::LineTo((x + r, y + height)));
+                    if r > 0. {
+                        primitives.push(corner((x, y + height - r)));
+                    }
+                    primitives.push(PathCommand::LineTo((x, y + r)));
+                    if r > 0. {
+                        primitives.push(corner((x + r, y)));
+                    }
+                    primitives.push(PathCommand::Close);
+                    current = (x + r, y);
+                    open = false;
+                }
+            }
+        }
+        primitives
+    }
+
+    /// Figures as polylines with the flag telling if the figure is closed. Curves are split
+    /// into line segments, so a backend without curves can draw or hit test the path
+    pub fn flatten(&self) -> Vec<(Vec<Point>, bool)> {
+        let mut figures = Vec::new();
+        let mut points: Vec<Point> = Vec::new();
+        for command in self.primitives() {
+            let current = points.last().copied().unwrap_or((0., 0.));
+            match command {
+                PathCommand::MoveTo(to) => {
+                    if !points.is_empty() {
+                        figures.push((std::mem::take(&mut points), false));
+                    }
+                    points.push(to);
+                }
+                PathCommand::LineTo(to) => points.push(to),
+                PathCommand::QuadTo { control, to } => {
+                    points.extend((1..=CURVE_SEGMENTS).map(|i| {
+                        let t = i as f32 / CURVE_SEGMENTS as f32;
+                        let r = 1. - t;
+                        (
+                            r * r * current.0 + 2. * r * t * control.0 + t * t * to.0,
+                            r * r * current.1 + 2. * r * t * control.1 + t * t * to.1,
+                        )
+                    }))
+                }
+                PathCommand::CubicTo {
+                    control1,
+                    control2,
+                    to,
+                } => points.extend((1..=CURVE_SEGMENTS).map(|i| {
+                    let t = i as f32 / CURVE_SEGMENTS as f32;
+                    let r = 1. - t;
+                    let (a, b, c, d) = (r * r * r, 3. * r * r * t, 3. * r * t * t, t * t * t);
+                    (
+                        a * current.0 + b * control1.0 + c * control2.0 + d * to.0,
+                        a * current.1 + b * control1.1 + c * control2.1 + d * to.1,
+                    )
+                })),
+                PathCommand::ArcTo {
+                    radius,
+                    rotation,
+                    large_arc,
+                    clockwise,
+                    to,
+                } => points.extend(arc_points(
+                    current, to, radius, rotation, large_arc, clockwise,
+                )),
+                PathCommand::Close => figures.push((std::mem::take(&mut points), true)),
+                PathCommand::Ellipse { .. } | PathCommand::RoundedRect { .. } => {}
+            }
+        }
+        if !points.is_empty() {
+            figures.push((points, false));
+        }
+        figures
+    }
+
+    /// Smallest rectangle containing the path, `None` for an empty path
+    pub fn bounds(&self) -> Option<Rect> {
+        let mut points = self
+            .flatten()
+            .into_iter()
+            .flat_map(|(points, _)| points.into_iter());
+        let first = points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), (x, y)| {
+            ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))
+        });
+        Some(Rect::new(min.0, min.1, max.0 - min.0, max.1 - min.1))
+    }
+}
+
+/// Points of the arc after `from`, by the endpoint to center conversion of the SVG spec
+fn arc_points(
+    from: Point,
+    to: Point,
+    radius: Point,
+    rotation: f32,
+    large_arc: bool,
+    clockwise: bool,
+) -> Vec<Point> {
+    let (mut rx, mut ry) = (radius.0.abs(), radius.1.abs());
+    if from == to {
+        return Vec::new();
+    }
+    if rx == 0. || ry == 0. {
+        return vec![to];
+    }
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let dx = (from.0 - to.0) / 2.;
+    let dy = (from.1 - to.1) / 2.;
+    let x1 = cos * dx + sin * dy;
+    let y1 = -sin * dx + cos * dy;
+    // Too small radii are scaled up so that the ellipse reaches the end point
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1. {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut coefficient = (numerator / denominator).max(0.).sqrt();
+    if large_arc == clockwise {
+        coefficient = -coefficient;
+    }
+    let cx1 = coefficient * rx * y1 / ry;
+    let cy1 = -coefficient * ry * x1 / rx;
+    let cx = cos * cx1 - sin * cy1 + (from.0 + to.0) / 2.;
+    let cy = sin * cx1 + cos * cy1 + (from.1 + to.1) / 2.;
+    let angle = |ux: f32, uy: f32, vx: f32, vy: f32| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+    let start = angle(1., 0., (x1 - cx1) / rx, (y1 - cy1) / ry);
+    let mut sweep = angle(
+        (x1 - cx1) / rx,
+        (y1 - cy1) / ry,
+        (-x1 - cx1) / rx,
+        (-y1 - cy1) / ry,
+    );
+    // The y axis goes down, so positive angles go clockwise
+    if clockwise && sweep < 0. {
+        sweep += 2. * PI;
+    } else if !clockwise && sweep > 0. {
+        sweep -= 2. * PI;
+    }
+    let mut points = (1..CURVE_SEGMENTS)
+        .map(|i| {
+            let (sin_t, cos_t) = (start + sweep * i as f32 / CURVE_SEGMENTS as f32).sin_cos();
+            (
+                cos * rx * cos_t - sin * ry * sin_t + cx,
+                sin * rx * cos_t + cos * ry * sin_t + cy,
+            )
+        })
+        .collect::<Vec<_>>();
+    // Exactly at the end point, without rounding errors
+    points.push(to);
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{arc_points, Path, PathCommand, Point, CURVE_SEGMENTS};
+
+    fn assert_near(actual: Point, expected: Point) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-3 && (actual.1 - expected.1).abs() < 1e-3,
+            "{:?} is not {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn ellipse_points_stay_on_ellipse() {
+        let figures = Path::ellipse((10., 20.), (8., 4.)).flatten();
+        assert_eq!(figures.len(), 1);
+        let (points, closed) = &figures[0];
+        assert!(closed);
+        assert_eq!(points.len(), 1 + 2 * CURVE_SEGMENTS);
+        for &(x, y) in points {
+            let distance = ((x - 10.) / 8.).powi(2) + ((y - 20.) / 4.).powi(2);
+            assert!((distance - 1.).abs() < 1e-3, "({}, {}) is off", x, y);
+        }
+    }
+
+    #[test]
+    fn arc_flags_choose_quadrant() {
+        // Circles of radius 10 through (10, 0) and (0, 10) are centered at (0, 0) and (10, 10)
+        let middle = |large_arc, clockwise| {
+            arc_points((10., 0.), (0., 10.), (10., 10.), 0., large_arc, clockwise)
+                [CURVE_SEGMENTS / 2 - 1]
+        };
+        let d = 10. * std::f32::consts::FRAC_1_SQRT_2;
+        assert_near(middle(false, true), (d, d));
+        assert_near(middle(false, false), (10. - d, 10. - d));
+        assert_near(middle(true, true), (10. + d, 10. + d));
+        assert_near(middle(true, false), (-d, -d));
+    }
+
+    #[test]
+    fn small_radius_is_scaled_up() {
+        let points = arc_points((0., 0.), (20., 0.), (1., 1.), 0., false, true);
+        for &(x, y) in &points {
+            assert!(
+                ((x - 10.).hypot(y) - 10.).abs() < 1e-3,
+                "({}, {}) is off",
+                x,
+                y
+            );
+        }
+        // Clockwise from the left end with the y axis going down passes above the center
+        assert_near(points[CURVE_SEGMENTS / 2 - 1], (10., -10.));
+        assert_eq!(points.last(), Some(&(20., 0.)));
+    }
+
+    #[test]
+    fn rounded_rect_radius_is_clamped() {
+        let primitives = Path::rounded_rect((0., 0.), (10., 4.), 5.).primitives();
+        assert_eq!(primitives[0], PathCommand::MoveTo((2., 0.)));
+        for command in &primitives {
+            if let PathCommand::ArcTo { radius, .. } = command {
+                assert_eq!(*radius, (2., 2.));
+            }
+        }
+        let bounds = Path::rounded_rect((0., 0.), (10., 4.), 5.)
+            .bounds()
+            .unwrap();
+        assert_near((bounds.width, bounds.height), (10., 4.));
+        // Negative radius gives sharp corners
+        let primitives = Path::rounded_rect((0., 0.), (10., 4.), -1.).primitives();
+        assert_eq!(primitives.len(), 6);
+        assert!(!primitives
+            .iter()
+            .any(|command| matches!(command, PathCommand::ArcTo { .. })));
+    }
+
+    #[test]
+    fn ellipse_bounds() {
+        let bounds = Path::ellipse((10., 20.), (8., 4.)).bounds().unwrap();
+        assert_near((bounds.x, bounds.y), (2., 16.));
+        assert_near((bounds.width, bounds.height), (16., 8.));
+        assert_eq!(Path::new().bounds(), None);
+    }
+}
@@ -5,15 +5,21 @@ use bindings::Windows::{
     Foundation::Numerics::{Vector2, Vector3},
     UI::Composition::ContainerVisual,
 };
-use futures::StreamExt;
+use futures::{Future, StreamExt};
+use serde::Deserialize;
 
 use crate::{
+    animation::Transition,
+    layout::{self, Alignment, Axis, LinearItem, LinearLimit, Overflow, StackItem, Thickness},
     slot::SlotPlug,
-    slot_event::{MouseLeftPressed, MouseLeftPressedFocused},
-    FrameTag, ReceiveSlotEvent, SendSlotEvent, SlotKeeper, SlotSize, SlotTag,
+    slot_event::{
+        FocusChanged, KeyPressed, MouseLeftPressed, MouseLeftPressedFocused, ThemeChanged,
+    },
+    AnimationTarget, AnimationValue, FrameTag, ReceiveSlotEvent, SendSlotEvent, SlotKeeper,
+    SlotSize, SlotTag, TaskGroup,
 };
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Clone, Copy, Debug, Deserialize)]
 pub enum RibbonOrientation {
     Stack,
     Horizontal,
@@ -26,6 +32,14 @@ pub struct CellLimit {
     pub content_ratio: Vector2,
     pub min_size: f32,
     pub max_size: Option<f32>,
+    /// Space around the cell, not included into `min_size` and `max_size`
+    pub margin: Thickness,
+    /// Alignment on the cross axis for `Horizontal` and `Vertical` ribbons, on both axes for `Stack`
+    pub align: Alignment,
+    /// Width / height ratio kept by the cell regardless of the space given to it
+    pub aspect_ratio: Option<f32>,
+    /// Size the cell by the desired size of its content instead of the ratio
+    pub auto_size: bool,
 }
 
 impl CellLimit {
@@ -35,6 +49,18 @@ impl CellLimit {
             content_ratio,
             min_size,
             max_size,
+            margin: Thickness::default(),
+            align: Alignment::default(),
+            aspect_ratio: None,
+            auto_size: false,
+        }
+    }
+
+    /// Cell sized by the desired size of its content, e.g. by the text of a label
+    pub fn auto() -> Self {
+        Self {
+            auto_size: true,
+            ..Self::default()
         }
     }
 
@@ -42,6 +68,47 @@ impl CellLimit {
         self.min_size = size;
         self.max_size = Some(size);
     }
+
+    pub fn set_margin(&mut self, margin: Thickness) {
+        self.margin = margin;
+    }
+
+    pub fn set_align(&mut self, align: Alignment) {
+        self.align = align;
+    }
+
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: Option<f32>) {
+        self.aspect_ratio = aspect_ratio;
+    }
+
+    pub fn set_auto_size(&mut self, auto_size: bool) {
+        self.auto_size = auto_size;
+    }
+
+    pub fn linear(&self) -> LinearLimit {
+        LinearLimit::new(self.ratio, self.min_size, self.max_size)
+    }
+
+    pub fn linear_item(&self) -> LinearItem {
+        LinearItem {
+            limit: self.linear(),
+            margin: self.margin,
+            content_ratio: (self.content_ratio.X, self.content_ratio.Y),
+            align: self.align,
+            aspect_ratio: self.aspect_ratio,
+            desired_size: None,
+        }
+    }
+
+    pub fn stack_item(&self) -> StackItem {
+        StackItem {
+            margin: self.margin,
+            content_ratio: (self.content_ratio.X, self.content_ratio.Y),
+            align: self.align,
+            aspect_ratio: self.aspect_ratio,
+            desired_size: None,
+        }
+    }
 }
 
 impl Default for CellLimit {
@@ -51,6 +118,10 @@ impl Default for CellLimit {
             content_ratio: Vector2::new(1., 1.),
             min_size: 0.,
             max_size: None,
+            margin: Thickness::default(),
+            align: Alignment::default(),
+            aspect_ratio: None,
+            auto_size: false,
         }
     }
 }
@@ -62,15 +133,9 @@ struct Cell {
 }
 
 impl Cell {
-    fn translate_point(&self, mut point: Vector2) -> crate::Result<Vector2> {
-        let offset = self.container.Offset()?;
-        point.X -= offset.X;
-        point.Y -= offset.Y;
-        Ok(point)
-    }
-    fn is_translated_point_in_cell(&self, point: Vector2) -> crate::Result<bool> {
-        let size = self.container.Size()?;
-        Ok(point.X >= 0. && point.X < size.X && point.Y >= 0. && point.Y < size.Y)
+    /// Hidden cells don't get mouse events
+    fn is_visible(&self) -> crate::Result<bool> {
+        Ok(self.container.IsVisible()?)
     }
 }
 
@@ -79,7 +144,14 @@ pub struct Ribbon {
     slot: SlotPlug,
     container: ContainerVisual,
     orientation: RibbonOrientation,
+    overflow: Overflow,
+    padding: Thickness,
+    spacing: f32,
+    /// Animation of cells moving to new positions. Sizes are not animated:
+    /// the content of cells is arranged for the final size at once
+    transition: Option<Transition>,
     cells: Vec<Cell>,
+    tasks: TaskGroup,
 }
 
 impl Ribbon {
@@ -89,96 +161,278 @@ impl Ribbon {
         orientation: RibbonOrientation,
     ) -> crate::Result<Self> {
         let container = frame.compositor()?.CreateContainerVisual()?;
-        let slot = slot.plug(container.clone().into())?;
+        let slot = slot.plug("Ribbon", container.clone().into())?;
+        let tasks = TaskGroup::for_slot(frame.clone(), &slot.tag())?;
         Ok(Self {
             frame,
             slot,
             container,
             orientation,
+            overflow: Overflow::default(),
+            padding: Thickness::default(),
+            spacing: 0.,
+            transition: None,
             cells: Vec::new(),
+            tasks,
         })
     }
 
     pub fn add_cell(&mut self, limit: CellLimit) -> crate::Result<SlotTag> {
+        self.insert_cell(self.cells.len(), limit)
+    }
+
+    /// Inserts a new cell before the cell at `index`. Index equal to the number of cells
+    /// appends the cell
+    pub fn insert_cell(&mut self, index: usize, limit: CellLimit) -> crate::Result<SlotTag> {
+        if index > self.cells.len() {
+            return Err(crate::Error::BadIndex);
+        }
         let compositor = self.frame.compositor()?;
         let container = compositor.CreateContainerVisual()?;
         let slot_keeper = SlotKeeper::new(container.clone())?;
-        self.container.Children()?.InsertAtTop(container.clone())?;
         let slot = slot_keeper.tag();
-        self.cells.push(Cell {
-            slot_keeper,
-            container,
-            limit,
-        });
-        self.resize_cells(self.container.Size()?)?;
+        self.attach_cell(
+            index,
+            Cell {
+                slot_keeper,
+                container,
+                limit,
+            },
+        )?;
+        self.relayout()?;
         Ok(slot)
     }
 
+    /// Removes the cell with its slot. Widgets in the slot stop receiving events
+    pub fn remove_cell(&mut self, index: usize) -> crate::Result<()> {
+        if index >= self.cells.len() {
+            return Err(crate::Error::BadIndex);
+        }
+        let cell = self.detach_cell(index)?;
+        self.frame.slot_removed(&cell.slot_keeper.tag())?;
+        self.relayout()
+    }
+
+    /// Moves the cell at `from` so that it ends up at index `to`
+    pub fn move_cell(&mut self, from: usize, to: usize) -> crate::Result<()> {
+        if from >= self.cells.len() || to >= self.cells.len() {
+            return Err(crate::Error::BadIndex);
+        }
+        let cell = self.detach_cell(from)?;
+        self.attach_cell(to, cell)?;
+        self.relayout()
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn cell_index(&self, slot: &SlotTag) -> Option<usize> {
+        self.cells
+            .iter()
+            .position(|cell| cell.slot_keeper.tag() == *slot)
+    }
+
+    pub fn cell_limit(&self, index: usize) -> crate::Result<CellLimit> {
+        self.cells
+            .get(index)
+            .map(|cell| cell.limit)
+            .ok_or(crate::Error::BadIndex)
+    }
+
+    pub fn set_cell_limit(&mut self, index: usize, limit: CellLimit) -> crate::Result<()> {
+        let cell = self.cells.get_mut(index).ok_or(crate::Error::BadIndex)?;
+        cell.limit = limit;
+        self.relayout()
+    }
+
+    /// Puts the cell's visual and slot to the position `index`, keeping z-order and
+    /// order of child slots the same as order of cells
+    fn attach_cell(&mut self, index: usize, cell: Cell) -> crate::Result<()> {
+        let children = self.container.Children()?;
+        let next = self.cells.get(index);
+        match next {
+            Some(next) => children.InsertBelow(cell.container.clone(), &next.container)?,
+            None => children.InsertAtTop(cell.container.clone())?,
+        }
+        let next_slot = next.map(|next| next.slot_keeper.tag());
+        self.slot
+            .tag()
+            .insert_child_before(cell.slot_keeper.tag(), next_slot.as_ref())?;
+        self.cells.insert(index, cell);
+        Ok(())
+    }
+
+    fn detach_cell(&mut self, index: usize) -> crate::Result<Cell> {
+        let cell = self.cells.remove(index);
+        self.container.Children()?.Remove(&cell.container)?;
+        self.slot.tag().remove_child(&cell.slot_keeper.tag())?;
+        Ok(cell)
+    }
+
+    /// Recalculates cells for the current size of the ribbon and notifies them
+    fn relayout(&mut self) -> crate::Result<()> {
+        self.resize_cells(self.container.Size()?)?;
+        for cell in &mut self.cells {
+            cell.slot_keeper
+                .send_size(SlotSize(cell.container.Size()?))?
+        }
+        Ok(())
+    }
+
+    pub fn set_overflow(&mut self, overflow: Overflow) -> crate::Result<()> {
+        self.overflow = overflow;
+        self.relayout()
+    }
+
+    /// Space between the ribbon's border and its cells
+    pub fn set_padding(&mut self, padding: Thickness) -> crate::Result<()> {
+        self.padding = padding;
+        self.relayout()
+    }
+
+    /// Space between neighbouring cells. Not used in `Stack` orientation
+    pub fn set_spacing(&mut self, spacing: f32) -> crate::Result<()> {
+        self.spacing = spacing;
+        self.relayout()
+    }
+
+    pub fn set_transition(&mut self, transition: Option<Transition>) {
+        self.transition = transition;
+    }
+
     fn resize_cells(&mut self, size: Vector2) -> crate::Result<()> {
-        if self.orientation == RibbonOrientation::Stack {
-            for cell in &self.cells {
-                let content_size = size.clone() * cell.limit.content_ratio.clone();
-                let content_offset = Vector3 {
-                    X: (size.X - content_size.X) / 2.,
-                    Y: (size.Y - content_size.Y) / 2.,
-                    Z: 0.,
-                };
-                cell.container.SetSize(&content_size)?;
-                cell.container.SetOffset(&content_offset)?;
-            }
-        } else {
-            let limits = self.cells.iter().map(|c| c.limit).collect::<Vec<_>>();
-            let hor = self.orientation == RibbonOrientation::Horizontal;
-            let target = if hor { size.X } else { size.Y };
-            let sizes = adjust_cells(limits, target);
-            let mut pos: f32 = 0.;
-            for i in 0..self.cells.len() {
-                let size = if hor {
-                    Vector2 {
-                        X: sizes[i],
-                        Y: size.Y,
-                    }
+        let _span = tracing::debug_span!(
+            "Ribbon::resize_cells",
+            slot = self.slot.tag().id()?,
+            orientation = ?self.orientation,
+            size = ?size
+        )
+        .entered();
+        // Measure: collect desired sizes of auto-sized cells
+        let mut desired_sizes = Vec::with_capacity(self.cells.len());
+        for cell in &self.cells {
+            desired_sizes.push(if cell.limit.auto_size {
+                cell.slot_keeper
+                    .tag()
+                    .desired_size()?
+                    .map(|size| (size.X, size.Y))
+            } else {
+                None
+            });
+        }
+        // Arrange
+        let (rects, (desired_width, desired_height)) =
+            if self.orientation == RibbonOrientation::Stack {
+                let items = self
+                    .cells
+                    .iter()
+                    .zip(desired_sizes)
+                    .map(|(cell, desired_size)| StackItem {
+                        desired_size,
+                        ..cell.limit.stack_item()
+                    })
+                    .collect::<Vec<_>>();
+                let rects = items
+                    .iter()
+                    .map(|item| layout::stack(size.X, size.Y, self.padding, item))
+                    .collect::<Vec<_>>();
+                (rects, layout::measure_stack(self.padding, &items))
+            } else {
+                let axis = if self.orientation == RibbonOrientation::Horizontal {
+                    Axis::Horizontal
                 } else {
-                    Vector2 {
-                        X: size.X,
-                        Y: sizes[i],
-                    }
+                    Axis::Vertical
                 };
-                let cell = &mut self.cells[i];
-                cell.container.SetSize(&size)?;
-                cell.container.SetOffset(if hor {
-                    Vector3 {
-                        X: pos,
-                        Y: 0.,
-                        Z: 0.,
-                    }
-                } else {
-                    Vector3 {
-                        X: 0.,
-                        Y: pos,
-                        Z: 0.,
-                    }
-                })?;
-                pos += sizes[i];
+                let items = self
+                    .cells
+                    .iter()
+                    .zip(desired_sizes)
+                    .map(|(cell, desired_size)| LinearItem {
+                        desired_size,
+                        ..cell.limit.linear_item()
+                    })
+                    .collect::<Vec<_>>();
+                let rects = layout::arrange(
+                    axis,
+                    size.X,
+                    size.Y,
+                    self.padding,
+                    self.spacing,
+                    &items,
+                    self.overflow,
+                )?;
+                (
+                    rects,
+                    layout::measure(axis, self.padding, self.spacing, &items),
+                )
+            };
+        tracing::debug!(rects = ?rects, "cell rects computed");
+        for (cell, rect) in self.cells.iter().zip(rects) {
+            // Cells which were never placed appear at their positions at once
+            let old_size = cell.container.Size()?;
+            let placed = old_size.X > 0. || old_size.Y > 0.;
+            cell.container.SetSize(Vector2 {
+                X: rect.width,
+                Y: rect.height,
+            })?;
+            let offset = Vector3 {
+                X: rect.x,
+                Y: rect.y,
+                Z: 0.,
+            };
+            match self.transition {
+                Some(transition) if placed => {
+                    self.frame.animate(
+                        AnimationTarget::Offset(cell.container.clone().into()),
+                        AnimationValue::Offset(offset),
+                        transition,
+                    )?;
+                }
+                _ => cell.container.SetOffset(offset)?,
             }
         }
+        // Report own desired size, so that the ribbon can be placed into an auto-sized cell
+        let desired_size = Vector2 {
+            X: desired_width,
+            Y: desired_height,
+        };
+        if self.slot.tag().set_desired_size(Some(desired_size))? {
+            tracing::debug!(desired_size = ?desired_size, "ribbon desired size changed");
+            self.frame.request_layout()?;
+        }
         Ok(())
     }
 }
 
+impl Drop for Ribbon {
+    fn drop(&mut self) {
+        // Cell slots are destroyed with the ribbon, so they shouldn't stay in the slot tree
+        // nor keep the focus, e.g. when a reloaded description replaces the ribbon
+        let slot = self.slot.tag();
+        for cell in &self.cells {
+            let _ = slot.remove_child(&cell.slot_keeper.tag());
+            let _ = self.frame.slot_removed(&cell.slot_keeper.tag());
+        }
+    }
+}
+
 impl SendSlotEvent for Ribbon {
     fn send_size(&mut self, event: SlotSize) -> crate::Result<()> {
-        self.resize_cells(event.0)?;
-        for cell in &mut self.cells {
-            cell.slot_keeper
-                .send_size(SlotSize(cell.container.Size()?))?
-        }
-        Ok(())
+        self.container.SetSize(event.0)?;
+        self.relayout()
     }
 
     fn send_mouse_left_pressed(&mut self, event: MouseLeftPressed) -> crate::Result<()> {
         for cell in &mut self.cells {
-            let point = cell.translate_point(event.0)?;
+            if !cell.is_visible()? {
+                continue;
+            }
+            let point = match cell.slot_keeper.tag().to_local(event.0)? {
+                Some(point) => point,
+                None => continue,
+            };
+            tracing::trace!(point = ?event.0, translated = ?point, "ribbon mouse left pressed");
             cell.slot_keeper
                 .send_mouse_left_pressed(MouseLeftPressed(point))?
         }
@@ -189,18 +443,46 @@ impl SendSlotEvent for Ribbon {
         &mut self,
         event: MouseLeftPressedFocused,
     ) -> crate::Result<()> {
-        for cell in &mut self.cells {
-            let point = cell.translate_point(event.0)?;
-            if cell.is_translated_point_in_cell(point)? {
+        // Cells added later are on top
+        for (index, cell) in self.cells.iter_mut().enumerate().rev() {
+            if let Some(hit) = cell.slot_keeper.tag().hit_test(event.0)?.first() {
+                tracing::debug!(
+                    point = ?event.0,
+                    cell = index,
+                    translated = ?hit.point,
+                    "ribbon mouse left pressed focused"
+                );
                 return cell
                     .slot_keeper
-                    .send_mouse_left_pressed_focused(MouseLeftPressedFocused(point));
+                    .send_mouse_left_pressed_focused(MouseLeftPressedFocused(hit.point));
             }
         }
         Ok(())
     }
+
+    fn send_key_pressed(&mut self, event: KeyPressed) -> crate::Result<()> {
+        for cell in &mut self.cells {
+            cell.slot_keeper.send_key_pressed(event.clone())?
+        }
+        Ok(())
+    }
+
+    fn send_focus_changed(&mut self, event: FocusChanged) -> crate::Result<()> {
+        for cell in &mut self.cells {
+            cell.slot_keeper.send_focus_changed(event.clone())?
+        }
+        Ok(())
+    }
+
+    fn send_theme_changed(&mut self, event: ThemeChanged) -> crate::Result<()> {
+        for cell in &mut self.cells {
+            cell.slot_keeper.send_theme_changed(event.clone())?
+        }
+        Ok(())
+    }
 }
 
+#[derive(Clone)]
 pub struct RibbonKeeper(Keeper<Ribbon>);
 
 impl RibbonKeeper {
@@ -222,14 +504,17 @@ impl RibbonKeeper {
     pub fn get_mut(&self) -> RwLockWriteGuard<'_, Ribbon> {
         self.0.get_mut()
     }
+    /// Resolves when event handlers of the ribbon are stopped, i.e. after the keeper is dropped
+    pub fn tasks_finished(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.get().tasks.finished()
+    }
     fn spawn_event_handlers(&self) -> crate::Result<()> {
-        let frame = self.0.get().frame.clone();
         let slot = self.0.get().slot.tag();
         let ribbon = self.tag();
         {
             let slot = slot.clone();
             let mut ribbon = ribbon.clone();
-            frame.thread_spawn(async move {
+            self.get_mut().tasks.spawn("Ribbon::on_size", async move {
                 while let Some(size) = slot.on_size().next().await {
                     ribbon.send_size(size)?
                 }
@@ -239,62 +524,53 @@ impl RibbonKeeper {
         {
             let slot = slot.clone();
             let mut ribbon = ribbon.clone();
-            frame.thread_spawn(async move {
-                while let Some(event) = slot.on_mouse_left_pressed().next().await {
-                    ribbon.send_mouse_left_pressed(event)?
-                }
-                Ok(())
-            })?;
+            self.get_mut()
+                .tasks
+                .spawn("Ribbon::on_mouse_left_pressed", async move {
+                    while let Some(event) = slot.on_mouse_left_pressed().next().await {
+                        ribbon.send_mouse_left_pressed(event)?
+                    }
+                    Ok(())
+                })?;
         }
-        Ok(())
-    }
-}
-
-fn adjust_cells(limits: Vec<CellLimit>, mut target: f32) -> Vec<f32> {
-    let mut lock = Vec::with_capacity(limits.len());
-    let mut result = Vec::with_capacity(limits.len());
-    lock.resize(limits.len(), false);
-    result.resize(limits.len(), 0.);
-
-    let mut sum_ratio = limits
-        .iter()
-        .map(|c| {
-            assert!(c.ratio > 0.);
-            c.ratio
-        })
-        .sum::<f32>();
-    loop {
-        let mut new_target = target;
-        let mut all_lock = true;
-        for i in 0..limits.len() {
-            if !lock[i] {
-                let mut share = target * limits[i].ratio / sum_ratio;
-                if share <= limits[i].min_size {
-                    share = limits[i].min_size;
-                    lock[i] = true;
-                }
-                if let Some(max_size) = limits[i].max_size {
-                    if share > max_size {
-                        share = max_size;
-                        lock[i] = true;
+        {
+            let slot = slot.clone();
+            let mut ribbon = ribbon.clone();
+            self.get_mut()
+                .tasks
+                .spawn("Ribbon::on_key_pressed", async move {
+                    while let Some(event) = slot.on_key_pressed().next().await {
+                        ribbon.send_key_pressed(event)?
                     }
-                }
-                if lock[i] {
-                    new_target -= share;
-                    sum_ratio -= limits[i].ratio;
-                    lock[i] = true;
-                } else {
-                    all_lock = false;
-                }
-                result[i] = share;
-            }
+                    Ok(())
+                })?;
         }
-        if all_lock || new_target == target {
-            break;
+        {
+            let slot = slot.clone();
+            let mut ribbon = ribbon.clone();
+            self.get_mut()
+                .tasks
+                .spawn("Ribbon::on_focus_changed", async move {
+                    while let Some(event) = slot.on_focus_changed().next().await {
+                        ribbon.send_focus_changed(event)?
+                    }
+                    Ok(())
+                })?;
+        }
+        {
+            let slot = slot.clone();
+            let mut ribbon = ribbon.clone();
+            self.get_mut()
+                .tasks
+                .spawn("Ribbon::on_theme_changed", async move {
+                    while let Some(event) = slot.on_theme_changed().next().await {
+                        ribbon.send_theme_changed(event)?
+                    }
+                    Ok(())
+                })?;
         }
-        target = if new_target > 0. { new_target } else { 0. };
+        Ok(())
     }
-    result
 }
 
 #[derive(Clone, PartialEq)]
@@ -307,6 +583,39 @@ impl RibbonTag {
     pub fn add_cell(&self, limit: CellLimit) -> crate::Result<SlotTag> {
         self.0.call_mut(|v| v.add_cell(limit))?
     }
+    pub fn insert_cell(&self, index: usize, limit: CellLimit) -> crate::Result<SlotTag> {
+        self.0.call_mut(|v| v.insert_cell(index, limit))?
+    }
+    pub fn remove_cell(&self, index: usize) -> crate::Result<()> {
+        self.0.call_mut(|v| v.remove_cell(index))?
+    }
+    pub fn move_cell(&self, from: usize, to: usize) -> crate::Result<()> {
+        self.0.call_mut(|v| v.move_cell(from, to))?
+    }
+    pub fn cell_count(&self) -> crate::Result<usize> {
+        Ok(self.0.call(|v| v.cell_count())?)
+    }
+    pub fn cell_index(&self, slot: &SlotTag) -> crate::Result<Option<usize>> {
+        Ok(self.0.call(|v| v.cell_index(slot))?)
+    }
+    pub fn cell_limit(&self, index: usize) -> crate::Result<CellLimit> {
+        self.0.call(|v| v.cell_limit(index))?
+    }
+    pub fn set_cell_limit(&self, index: usize, limit: CellLimit) -> crate::Result<()> {
+        self.0.call_mut(|v| v.set_cell_limit(index, limit))?
+    }
+    pub fn set_overflow(&self, overflow: Overflow) -> crate::Result<()> {
+        self.0.call_mut(|v| v.set_overflow(overflow))?
+    }
+    pub fn set_padding(&self, padding: Thickness) -> crate::Result<()> {
+        self.0.call_mut(|v| v.set_padding(padding))?
+    }
+    pub fn set_spacing(&self, spacing: f32) -> crate::Result<()> {
+        self.0.call_mut(|v| v.set_spacing(spacing))?
+    }
+    pub fn set_transition(&self, transition: Option<Transition>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_transition(transition))?)
+    }
 }
 
 impl SendSlotEvent for RibbonTag {
@@ -325,4 +634,16 @@ impl SendSlotEvent for RibbonTag {
         self.0
             .call_mut(|v| v.send_mouse_left_pressed_focused(event))?
     }
+
+    fn send_key_pressed(&mut self, event: KeyPressed) -> crate::Result<()> {
+        self.0.call_mut(|v| v.send_key_pressed(event))?
+    }
+
+    fn send_focus_changed(&mut self, event: FocusChanged) -> crate::Result<()> {
+        self.0.call_mut(|v| v.send_focus_changed(event))?
+    }
+
+    fn send_theme_changed(&mut self, event: ThemeChanged) -> crate::Result<()> {
+        self.0.call_mut(|v| v.send_theme_changed(event))?
+    }
 }
@@ -0,0 +1,303 @@
+use std::sync::{Arc, RwLockReadGuard, RwLockWriteGuard};
+
+use async_object::{Keeper, Tag};
+use bindings::{
+    Microsoft::Graphics::Canvas::Geometry::{
+        CanvasArcSize, CanvasFigureLoop, CanvasGeometry, CanvasPathBuilder, CanvasSweepDirection,
+    },
+    Windows::{
+        Foundation::Numerics::Vector2,
+        UI::Composition::{CompositionPath, ShapeVisual},
+    },
+};
+use futures::{Future, StreamExt};
+
+use crate::{
+    layout::Rect,
+    path::{Path, PathCommand},
+    slot::SlotPlug,
+    FrameTag, ReceiveSlotEvent, SlotTag, TaskGroup, Theme, ThemeColor,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stroke {
+    /// In pixels of the slot, not scaled with the path
+    pub width: f32,
+    pub color: ThemeColor,
+}
+
+impl Stroke {
+    pub fn new(width: f32, color: impl Into<ThemeColor>) -> Self {
+        Self {
+            width,
+            color: color.into(),
+        }
+    }
+}
+
+fn vector((x, y): (f32, f32)) -> Vector2 {
+    Vector2 { X: x, Y: y }
+}
+
+/// Vector path scaled to the slot keeping its aspect ratio and centered
+pub struct Shape {
+    frame: FrameTag,
+    slot: SlotPlug,
+    visual: ShapeVisual,
+    path: Path,
+    /// Area of the path's coordinates fitted into the slot. By default the path's bounds
+    view_box: Option<Rect>,
+    fill: Option<ThemeColor>,
+    stroke: Option<Stroke>,
+    theme: Arc<Theme>,
+    tasks: TaskGroup,
+}
+
+impl Shape {
+    fn new(
+        frame: FrameTag,
+        slot: SlotTag,
+        path: Path,
+        fill: Option<ThemeColor>,
+        stroke: Option<Stroke>,
+    ) -> crate::Result<Self> {
+        let visual = frame.compositor()?.CreateShapeVisual()?;
+        visual.SetSize(slot.container()?.Size()?)?;
+        let theme = frame.theme()?;
+        let slot = slot.plug("Shape", visual.clone().into())?;
+        let tasks = TaskGroup::for_slot(frame.clone(), &slot.tag())?;
+        let shape = Self {
+            frame,
+            slot,
+            visual,
+            path,
+            view_box: None,
+            fill,
+            stroke,
+            theme,
+            tasks,
+        };
+        shape.redraw()?;
+        Ok(shape)
+    }
+
+    fn set_path(&mut self, path: Path) -> crate::Result<()> {
+        self.path = path;
+        self.redraw()
+    }
+
+    fn set_view_box(&mut self, view_box: Option<Rect>) -> crate::Result<()> {
+        self.view_box = view_box;
+        self.redraw()
+    }
+
+    fn set_fill(&mut self, fill: Option<ThemeColor>) -> crate::Result<()> {
+        self.fill = fill;
+        self.redraw()
+    }
+
+    fn set_stroke(&mut self, stroke: Option<Stroke>) -> crate::Result<()> {
+        self.stroke = stroke;
+        self.redraw()
+    }
+
+    fn set_theme(&mut self, theme: Arc<Theme>) -> crate::Result<()> {
+        self.theme = theme;
+        self.redraw()
+    }
+
+    fn set_size(&mut self, size: Vector2) -> crate::Result<()> {
+        tracing::trace!(size = ?size, "shape size");
+        self.visual.SetSize(size)?;
+        self.redraw()
+    }
+
+    fn redraw(&self) -> crate::Result<()> {
+        self.visual.Shapes()?.Clear()?;
+        let view_box = match self.view_box.or_else(|| self.path.bounds()) {
+            Some(view_box) => view_box,
+            None => return Ok(()),
+        };
+        // The stroke goes out of the path by half of its width on each side
+        let stroke_width = self.stroke.as_ref().map_or(0., |stroke| stroke.width);
+        let size = self.visual.Size()?;
+        let available = Vector2 {
+            X: (size.X - stroke_width).max(0.),
+            Y: (size.Y - stroke_width).max(0.),
+        };
+        // A straight line has no width or height, it's scaled by the other side only
+        let scale = match (view_box.width > 0., view_box.height > 0.) {
+            (true, true) => (available.X / view_box.width).min(available.Y / view_box.height),
+            (true, false) => available.X / view_box.width,
+            (false, true) => available.Y / view_box.height,
+            (false, false) => 1.,
+        };
+        let compositor = self.frame.compositor()?;
+        let path = CompositionPath::Create(self.create_geometry()?)?;
+        let geometry = compositor.CreatePathGeometryWithPath(path)?;
+        let sprite = compositor.CreateSpriteShapeWithGeometry(geometry)?;
+        if let Some(fill) = &self.fill {
+            sprite.SetFillBrush(compositor.CreateColorBrushWithColor(self.theme.resolve(fill))?)?;
+        }
+        if let Some(stroke) = &self.stroke {
+            let brush = compositor.CreateColorBrushWithColor(self.theme.resolve(&stroke.color))?;
+            sprite.SetStrokeBrush(brush)?;
+            sprite.SetStrokeThickness(stroke.width)?;
+            sprite.SetIsStrokeNonScaling(true)?;
+        }
+        sprite.SetScale(Vector2 { X: scale, Y: scale })?;
+        sprite.SetOffset(Vector2 {
+            X: (size.X - view_box.width * scale) / 2. - view_box.x * scale,
+            Y: (size.Y - view_box.height * scale) / 2. - view_box.y * scale,
+        })?;
+        self.visual.Shapes()?.Append(sprite)?;
+        Ok(())
+    }
+
+    fn create_geometry(&self) -> crate::Result<CanvasGeometry> {
+        let builder = CanvasPathBuilder::Create(&self.frame.canvas_device()?)?;
+        let mut open = false;
+        for command in self.path.primitives() {
+            match command {
+                PathCommand::MoveTo(to) => {
+                    if open {
+                        builder.EndFigure(CanvasFigureLoop::Open)?;
+                    }
+                    builder.BeginFigure(vector(to))?;
+                    open = true;
+                }
+                PathCommand::LineTo(to) => builder.AddLine(vector(to))?,
+                PathCommand::QuadTo { control, to } => {
+                    builder.AddQuadraticBezier(vector(control), vector(to))?
+                }
+                PathCommand::CubicTo {
+                    control1,
+                    control2,
+                    to,
+                } => builder.AddCubicBezier(vector(control1), vector(control2), vector(to))?,
+                PathCommand::ArcTo {
+                    radius,
+                    rotation,
+                    large_arc,
+                    clockwise,
+                    to,
+                } => builder.AddArcToPoint(
+                    vector(to),
+                    radius.0,
+                    radius.1,
+                    rotation.to_radians(),
+                    if clockwise {
+                        CanvasSweepDirection::Clockwise
+                    } else {
+                        CanvasSweepDirection::CounterClockwise
+                    },
+                    if large_arc {
+                        CanvasArcSize::Large
+                    } else {
+                        CanvasArcSize::Small
+                    },
+                )?,
+                PathCommand::Close => {
+                    builder.EndFigure(CanvasFigureLoop::Closed)?;
+                    open = false;
+                }
+                // Replaced by lines and arcs in primitives
+                PathCommand::Ellipse { .. } | PathCommand::RoundedRect { .. } => {}
+            }
+        }
+        if open {
+            builder.EndFigure(CanvasFigureLoop::Open)?;
+        }
+        Ok(CanvasGeometry::CreatePath(&builder)?)
+    }
+}
+
+#[derive(Clone)]
+pub struct ShapeKeeper(Keeper<Shape>);
+
+impl ShapeKeeper {
+    pub fn new(
+        frame: FrameTag,
+        slot: SlotTag,
+        path: Path,
+        fill: Option<ThemeColor>,
+        stroke: Option<Stroke>,
+    ) -> crate::Result<Self> {
+        let keeper = Self(Keeper::new(Shape::new(frame, slot, path, fill, stroke)?));
+        keeper.spawn_event_handlers()?;
+        Ok(keeper)
+    }
+    pub fn tag(&self) -> ShapeTag {
+        ShapeTag(self.0.tag())
+    }
+    pub fn get(&self) -> RwLockReadGuard<'_, Shape> {
+        self.0.get()
+    }
+    pub fn get_mut(&self) -> RwLockWriteGuard<'_, Shape> {
+        self.0.get_mut()
+    }
+    /// Resolves when event handlers of the shape are stopped, i.e. after all keepers are dropped
+    pub fn tasks_finished(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.get().tasks.finished()
+    }
+    fn spawn_event_handlers(&self) -> crate::Result<()> {
+        let tag = self.tag();
+        let slot = self.get().slot.tag();
+        {
+            let tag = tag.clone();
+            let slot = slot.clone();
+            self.get_mut().tasks.spawn("Shape::on_size", async move {
+                while let Some(size) = slot.on_size().next().await {
+                    tag.set_size(size.0)?;
+                }
+                Ok(())
+            })?;
+        }
+        self.get_mut()
+            .tasks
+            .spawn("Shape::on_theme_changed", async move {
+                while let Some(event) = slot.on_theme_changed().next().await {
+                    tag.set_theme(event.0)?;
+                }
+                Ok(())
+            })
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ShapeTag(Tag<Shape>);
+
+impl ShapeTag {
+    pub fn path(&self) -> crate::Result<Path> {
+        Ok(self.0.call(|v| v.path.clone())?)
+    }
+    pub fn set_path(&self, path: Path) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_path(path))??)
+    }
+    pub fn view_box(&self) -> crate::Result<Option<Rect>> {
+        Ok(self.0.call(|v| v.view_box)?)
+    }
+    /// Area of the path's coordinates fitted into the slot, e.g. the whole canvas of an icon.
+    /// `None` fits the bounds of the path
+    pub fn set_view_box(&self, view_box: Option<Rect>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_view_box(view_box))??)
+    }
+    pub fn fill(&self) -> crate::Result<Option<ThemeColor>> {
+        Ok(self.0.call(|v| v.fill.clone())?)
+    }
+    pub fn set_fill(&self, fill: Option<ThemeColor>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_fill(fill))??)
+    }
+    pub fn stroke(&self) -> crate::Result<Option<Stroke>> {
+        Ok(self.0.call(|v| v.stroke.clone())?)
+    }
+    pub fn set_stroke(&self, stroke: Option<Stroke>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_stroke(stroke))??)
+    }
+    fn set_theme(&self, theme: Arc<Theme>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_theme(theme))??)
+    }
+    pub fn set_size(&self, size: Vector2) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_size(size))??)
+    }
+}
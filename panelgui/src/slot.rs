@@ -1,33 +1,95 @@
-use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+};
 
 use async_object::{EventStream, Keeper, Tag};
-use bindings::Windows::UI::Composition::{ContainerVisual, Visual};
+use bindings::Windows::{
+    Foundation::Numerics::{Vector2, Vector3},
+    UI::Composition::{CompositionClip, ContainerVisual, Visual},
+};
 use futures::StreamExt;
 
 use crate::slot_event::{
-    MouseLeftPressed, MouseLeftPressedFocused, ReceiveSlotEvent, SendSlotEvent, SlotSize,
+    FocusChanged, KeyPressed, MouseLeftPressed, MouseLeftPressedFocused, ReceiveSlotEvent,
+    SendSlotEvent, SlotFocused, SlotSize, ThemeChanged,
 };
 
+static NEXT_SLOT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Slot under a point, with the point in coordinates of the slot
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hit {
+    pub slot: SlotTag,
+    pub point: Vector2,
+}
+
+/// Point given in coordinates of a visual's parent in coordinates of the visual with
+/// the offset, scale and center point. Rotation is not taken into account.
+/// Returns `None` if the visual is scaled to nothing
+fn to_local(offset: Vector3, scale: Vector3, center: Vector3, point: Vector2) -> Option<Vector2> {
+    if scale.X == 0. || scale.Y == 0. {
+        return None;
+    }
+    Some(Vector2 {
+        X: (point.X - offset.X - center.X) / scale.X + center.X,
+        Y: (point.Y - offset.Y - center.Y) / scale.Y + center.Y,
+    })
+}
+
 #[derive(Clone)]
 pub struct Slot {
+    id: usize,
     tag: SlotTag,
     container: ContainerVisual,
+    children: Vec<SlotTag>,
+    focusable: bool,
+    focused: bool,
+    clip_to_bounds: bool,
+    desired_size: Option<Vector2>,
+    /// Types of the widgets plugged into the slot, for diagnostics
+    widgets: Vec<&'static str>,
 }
 
 impl Slot {
     fn new(container: ContainerVisual) -> crate::Result<Self> {
         Ok(Self {
+            id: NEXT_SLOT_ID.fetch_add(1, Ordering::Relaxed),
             tag: SlotTag::default(),
             container,
+            children: Vec::new(),
+            focusable: false,
+            focused: false,
+            clip_to_bounds: false,
+            desired_size: None,
+            widgets: Vec::new(),
         })
     }
-    pub fn plug(&mut self, visual: Visual) -> crate::Result<SlotPlug> {
+    fn set_clip_to_bounds(&mut self, clip: bool) -> crate::Result<()> {
+        if clip {
+            // Inset clip with zero insets follows the size of the visual
+            let clip = self.container.Compositor()?.CreateInsetClip()?;
+            self.container.SetClip(clip)?;
+        } else {
+            self.container.SetClip(None::<CompositionClip>)?;
+        }
+        self.clip_to_bounds = clip;
+        Ok(())
+    }
+    /// Puts the widget's visual into the slot. `widget` is the type of the widget,
+    /// e.g. "Text", it's shown by diagnostics only
+    pub fn plug(&mut self, widget: &'static str, visual: Visual) -> crate::Result<SlotPlug> {
         visual.SetSize(self.container.Size()?)?;
         self.container.Children()?.InsertAtTop(visual.clone())?;
+        self.widgets.push(widget);
         Ok(SlotPlug {
             tag: self.tag.clone(),
             container: self.container.clone(),
             visual,
+            widget,
         })
     }
 }
@@ -36,6 +98,7 @@ pub struct SlotPlug {
     tag: SlotTag,
     container: ContainerVisual,
     visual: Visual,
+    widget: &'static str,
 }
 
 impl SlotPlug {
@@ -53,6 +116,12 @@ impl From<SlotPlug> for SlotTag {
 impl Drop for SlotPlug {
     fn drop(&mut self) {
         let _ = self.container.Children().map(|c| c.Remove(&self.visual));
+        let widget = self.widget;
+        let _ = self.tag.0.call_mut(|v| {
+            if let Some(index) = v.widgets.iter().position(|w| *w == widget) {
+                v.widgets.remove(index);
+            }
+        });
     }
 }
 
@@ -66,6 +135,7 @@ impl SlotKeeper {
             Arc::new(RwLock::new(container)),
         ));
         keeper.get_mut().tag = keeper.tag();
+        tracing::debug!(slot = keeper.get().id, "slot opened");
         Ok(keeper)
     }
     pub fn tag(&self) -> SlotTag {
@@ -82,8 +152,15 @@ impl SlotKeeper {
     }
 }
 
+impl Drop for SlotKeeper {
+    fn drop(&mut self) {
+        tracing::debug!(slot = self.get().id, "slot closed");
+    }
+}
+
 impl SendSlotEvent for SlotKeeper {
     fn send_size(&mut self, event: SlotSize) -> crate::Result<()> {
+        tracing::trace!(slot = self.get().id, size = ?event.0, "slot size");
         self.container()?.SetSize(event.0)?;
         self.0.send_event(event);
         Ok(())
@@ -99,19 +176,169 @@ impl SendSlotEvent for SlotKeeper {
         self.0.send_event(event);
         Ok(())
     }
+    fn send_key_pressed(&mut self, event: KeyPressed) -> crate::Result<()> {
+        self.0.send_event(event);
+        Ok(())
+    }
+    fn send_focus_changed(&mut self, event: FocusChanged) -> crate::Result<()> {
+        let focused = event.0.as_ref() == Some(&self.tag());
+        if self.get().focused != focused {
+            tracing::debug!(slot = self.get().id, focused, "slot focus");
+            self.get_mut().focused = focused;
+            self.0.send_event(SlotFocused(focused));
+        }
+        self.0.send_event(event);
+        Ok(())
+    }
+    fn send_theme_changed(&mut self, event: ThemeChanged) -> crate::Result<()> {
+        self.0.send_event(event);
+        Ok(())
+    }
 }
 
 #[derive(Clone, PartialEq, Default)]
 pub struct SlotTag(Tag<Slot, ContainerVisual>);
 
+impl fmt::Debug for SlotTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.id() {
+            Ok(id) => write!(f, "SlotTag({})", id),
+            Err(_) => write!(f, "SlotTag(destroyed)"),
+        }
+    }
+}
+
 impl SlotTag {
     pub async fn wait_for_destroy(&self) -> crate::Result<()> {
         let mut stream = EventStream::<()>::new(self.0.clone());
         while let Some(_) = stream.next().await {}
         Ok(())
     }
-    pub fn plug(&self, visual: Visual) -> crate::Result<SlotPlug> {
-        Ok(self.0.call_mut(|v| v.plug(visual))??)
+    pub fn plug(&self, widget: &'static str, visual: Visual) -> crate::Result<SlotPlug> {
+        Ok(self.0.call_mut(|v| v.plug(widget, visual))??)
+    }
+    pub fn container(&self) -> crate::Result<ContainerVisual> {
+        Ok(self.0.read_shared(|v| v.clone())?)
+    }
+    /// Unique slot number, useful for diagnostics only
+    pub fn id(&self) -> crate::Result<usize> {
+        Ok(self.0.call(|v| v.id)?)
+    }
+    /// Types of the widgets plugged into the slot, in the order they were plugged
+    pub fn widgets(&self) -> crate::Result<Vec<&'static str>> {
+        Ok(self.0.call(|v| v.widgets.clone())?)
+    }
+    /// Child slots in tree order, i.e. in the order they were added by the widget
+    /// which owns them
+    pub fn children(&self) -> crate::Result<Vec<SlotTag>> {
+        Ok(self.0.call(|v| v.children.clone())?)
+    }
+    pub fn add_child(&self, child: SlotTag) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.children.push(child))?)
+    }
+    /// Inserts the child before `next`, or appends it if `next` is `None` or not a child
+    pub fn insert_child_before(&self, child: SlotTag, next: Option<&SlotTag>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| {
+            let index = next
+                .and_then(|next| v.children.iter().position(|c| c == next))
+                .unwrap_or(v.children.len());
+            v.children.insert(index, child)
+        })?)
+    }
+    pub fn remove_child(&self, child: &SlotTag) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.children.retain(|c| c != child))?)
+    }
+    pub fn opacity(&self) -> crate::Result<f32> {
+        Ok(self.container()?.Opacity()?)
+    }
+    /// Opacity of the slot with all its content, from 0 (transparent) to 1 (opaque).
+    /// It can be animated with `AnimationTarget::Opacity` of the slot's container
+    pub fn set_opacity(&self, opacity: f32) -> crate::Result<()> {
+        Ok(self.container()?.SetOpacity(opacity.clamp(0., 1.))?)
+    }
+    pub fn is_visible(&self) -> crate::Result<bool> {
+        Ok(self.container()?.IsVisible()?)
+    }
+    /// Hidden slots are not drawn, don't get mouse events and can't be focused
+    pub fn set_visible(&self, visible: bool) -> crate::Result<()> {
+        tracing::debug!(slot = self.id()?, visible, "slot visibility");
+        Ok(self.container()?.SetIsVisible(visible)?)
+    }
+    pub fn clips_to_bounds(&self) -> crate::Result<bool> {
+        Ok(self.0.call(|v| v.clip_to_bounds)?)
+    }
+    /// Cuts off the content drawn outside of the slot, e.g. tiles sliding in from outside
+    pub fn set_clip_to_bounds(&self, clip: bool) -> crate::Result<()> {
+        self.0.call_mut(|v| v.set_clip_to_bounds(clip))?
+    }
+    /// Point given in coordinates of the slot's parent in coordinates of the slot,
+    /// see `Hit`. Returns `None` if the slot is scaled to nothing
+    pub fn to_local(&self, point: Vector2) -> crate::Result<Option<Vector2>> {
+        let container = self.container()?;
+        Ok(to_local(
+            container.Offset()?,
+            container.Scale()?,
+            container.CenterPoint()?,
+            point,
+        ))
+    }
+    /// True if the point given in coordinates of the slot is inside of it
+    pub fn contains(&self, point: Vector2) -> crate::Result<bool> {
+        let size = self.container()?.Size()?;
+        Ok(point.X >= 0. && point.X < size.X && point.Y >= 0. && point.Y < size.Y)
+    }
+    /// Chain of slots under the point, from this slot down to the deepest one. The point is
+    /// given in coordinates of the slot's parent. Children added later are on top, so they
+    /// are checked first. Hidden slots are skipped and content of clipping slots is hit only
+    /// inside of them, while content of other slots may be hit outside too.
+    /// The chain is empty if the point misses the slot
+    pub fn hit_test(&self, point: Vector2) -> crate::Result<Vec<Hit>> {
+        if !self.is_visible()? {
+            return Ok(Vec::new());
+        }
+        let point = match self.to_local(point)? {
+            Some(point) => point,
+            None => return Ok(Vec::new()),
+        };
+        let inside = self.contains(point)?;
+        if !inside && self.clips_to_bounds()? {
+            return Ok(Vec::new());
+        }
+        let hit = Hit {
+            slot: self.clone(),
+            point,
+        };
+        for child in self.children()?.iter().rev() {
+            // Slots destroyed by their owners are missed
+            let mut chain = child.hit_test(point).unwrap_or_default();
+            if !chain.is_empty() {
+                chain.insert(0, hit);
+                return Ok(chain);
+            }
+        }
+        Ok(if inside { vec![hit] } else { Vec::new() })
+    }
+    pub fn is_focusable(&self) -> crate::Result<bool> {
+        Ok(self.0.call(|v| v.focusable)?)
+    }
+    pub fn set_focusable(&self, focusable: bool) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.focusable = focusable)?)
+    }
+    pub fn is_focused(&self) -> crate::Result<bool> {
+        Ok(self.0.call(|v| v.focused)?)
+    }
+    /// Size the content of the slot wants to have, reported by the widget in the slot
+    pub fn desired_size(&self) -> crate::Result<Option<Vector2>> {
+        Ok(self.0.call(|v| v.desired_size)?)
+    }
+    /// Returns true if the size has changed. The widget should call `FrameTag::request_layout`
+    /// then, so that the parent gets a chance to take the new size into account
+    pub fn set_desired_size(&self, size: Option<Vector2>) -> crate::Result<bool> {
+        Ok(self.0.call_mut(|v| {
+            let changed = v.desired_size != size;
+            v.desired_size = size;
+            changed
+        })?)
     }
 }
 
@@ -127,4 +354,77 @@ impl ReceiveSlotEvent for SlotTag {
     fn on_mouse_left_pressed_focused(&self) -> EventStream<MouseLeftPressedFocused> {
         EventStream::new(self.0.clone())
     }
+
+    fn on_key_pressed(&self) -> EventStream<KeyPressed> {
+        EventStream::new(self.0.clone())
+    }
+
+    fn on_focus_changed(&self) -> EventStream<FocusChanged> {
+        EventStream::new(self.0.clone())
+    }
+
+    fn on_focused(&self) -> EventStream<SlotFocused> {
+        EventStream::new(self.0.clone())
+    }
+
+    fn on_theme_changed(&self) -> EventStream<ThemeChanged> {
+        EventStream::new(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bindings::Windows::Foundation::Numerics::{Vector2, Vector3};
+
+    use super::to_local;
+
+    fn point(x: f32, y: f32) -> Vector2 {
+        Vector2 { X: x, Y: y }
+    }
+
+    fn vector(x: f32, y: f32) -> Vector3 {
+        Vector3 { X: x, Y: y, Z: 0. }
+    }
+
+    #[test]
+    fn offset_is_subtracted() {
+        let identity = Vector3 {
+            X: 1.,
+            Y: 1.,
+            Z: 1.,
+        };
+        assert_eq!(
+            to_local(vector(10., 20.), identity, vector(0., 0.), point(15., 25.)),
+            Some(point(5., 5.))
+        );
+    }
+
+    #[test]
+    fn scale_is_around_center_point() {
+        let scale = vector(2., 4.);
+        let center = vector(10., 10.);
+        // The center point stays in place
+        assert_eq!(
+            to_local(vector(0., 0.), scale, center, point(10., 10.)),
+            Some(point(10., 10.))
+        );
+        assert_eq!(
+            to_local(vector(0., 0.), scale, center, point(30., 50.)),
+            Some(point(20., 20.))
+        );
+        assert_eq!(
+            to_local(vector(5., 5.), scale, center, point(35., 55.)),
+            Some(point(20., 20.))
+        );
+    }
+
+    #[test]
+    fn zero_scale_hits_nothing() {
+        for scale in [vector(0., 1.), vector(1., 0.), vector(0., 0.)] {
+            assert_eq!(
+                to_local(vector(0., 0.), scale, vector(0., 0.), point(1., 1.)),
+                None
+            );
+        }
+    }
 }
@@ -1,6 +1,10 @@
+use std::sync::Arc;
+
 use async_object::EventStream;
 use bindings::Windows::Foundation::Numerics::Vector2;
 
+use crate::{SlotTag, Theme};
+
 #[derive(Clone, Debug)]
 pub struct SlotSize(pub Vector2);
 
@@ -10,6 +14,37 @@ pub struct MouseLeftPressed(pub Vector2);
 #[derive(Clone, Debug)]
 pub struct MouseLeftPressedFocused(pub Vector2);
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Key {
+    Tab,
+    Enter,
+    Escape,
+    Space,
+    Left,
+    Up,
+    Right,
+    Down,
+    Other(u32),
+}
+
+#[derive(Clone, Debug)]
+pub struct KeyPressed {
+    pub key: Key,
+    pub shift: bool,
+}
+
+/// Broadcasted to the whole slot tree when keyboard focus moves to another slot (or nowhere)
+#[derive(Clone)]
+pub struct FocusChanged(pub Option<SlotTag>);
+
+/// Sent to the slot itself when it gets (true) or loses (false) keyboard focus
+#[derive(Clone, Debug)]
+pub struct SlotFocused(pub bool);
+
+/// Broadcasted to the whole slot tree when the frame's theme is changed
+#[derive(Clone, Debug)]
+pub struct ThemeChanged(pub Arc<Theme>);
+
 pub trait SendSlotEvent {
     fn send_size(&mut self, event: SlotSize) -> crate::Result<()>;
     fn send_mouse_left_pressed(&mut self, event: MouseLeftPressed) -> crate::Result<()>;
@@ -17,10 +52,17 @@ pub trait SendSlotEvent {
         &mut self,
         event: MouseLeftPressedFocused,
     ) -> crate::Result<()>;
+    fn send_key_pressed(&mut self, event: KeyPressed) -> crate::Result<()>;
+    fn send_focus_changed(&mut self, event: FocusChanged) -> crate::Result<()>;
+    fn send_theme_changed(&mut self, event: ThemeChanged) -> crate::Result<()>;
 }
 
 pub trait ReceiveSlotEvent {
     fn on_size(&self) -> EventStream<SlotSize>;
     fn on_mouse_left_pressed(&self) -> EventStream<MouseLeftPressed>;
     fn on_mouse_left_pressed_focused(&self) -> EventStream<MouseLeftPressedFocused>;
+    fn on_key_pressed(&self) -> EventStream<KeyPressed>;
+    fn on_focus_changed(&self) -> EventStream<FocusChanged>;
+    fn on_focused(&self) -> EventStream<SlotFocused>;
+    fn on_theme_changed(&self) -> EventStream<ThemeChanged>;
 }
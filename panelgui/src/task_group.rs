@@ -0,0 +1,89 @@
+use futures::{
+    channel::oneshot,
+    future::{join_all, AbortHandle, Abortable, Shared},
+    Future, FutureExt,
+};
+
+use crate::{FrameTag, SlotTag};
+
+struct Task {
+    abort_handle: AbortHandle,
+    done: Shared<oneshot::Receiver<()>>,
+}
+
+/// Tasks spawned on the frame on behalf of one widget. The widget owns the group,
+/// so its tasks are cancelled when the widget's keeper is dropped.
+pub struct TaskGroup {
+    frame: FrameTag,
+    /// Id of the widget's slot, reported with errors of the tasks
+    slot: Option<usize>,
+    tasks: Vec<Task>,
+}
+
+impl TaskGroup {
+    /// Group of tasks not bound to a slot, e.g. of a file watcher
+    pub fn new(frame: FrameTag) -> Self {
+        Self {
+            frame,
+            slot: None,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Group of tasks of the widget in the slot. Errors of the tasks tell the slot,
+    /// so that the failed widget instance can be found
+    pub fn for_slot(frame: FrameTag, slot: &SlotTag) -> crate::Result<Self> {
+        Ok(Self {
+            frame,
+            slot: Some(slot.id()?),
+            tasks: Vec::new(),
+        })
+    }
+
+    pub fn spawn<Fut>(&mut self, origin: impl Into<String>, future: Fut) -> crate::Result<()>
+    where
+        Fut: Future<Output = crate::Result<()>> + Send + 'static,
+    {
+        let (abort_handle, registration) = AbortHandle::new_pair();
+        let (done_sender, done) = oneshot::channel();
+        let future = Abortable::new(future, registration);
+        self.frame.slot_spawn(self.slot, origin, async move {
+            // Cancellation is not an error
+            let result = future.await.unwrap_or(Ok(()));
+            let _ = done_sender.send(());
+            result
+        })?;
+        // Polling a clone checks if the task is done: `peek` alone sees nothing until
+        // some clone is polled
+        self.tasks
+            .retain(|task| task.done.clone().now_or_never().is_none());
+        self.tasks.push(Task {
+            abort_handle,
+            done: done.shared(),
+        });
+        Ok(())
+    }
+
+    pub fn cancel(&mut self) {
+        for task in &self.tasks {
+            task.abort_handle.abort();
+        }
+    }
+
+    /// Resolves when all tasks spawned so far are finished or cancelled.
+    /// The future doesn't keep the group alive, so it can be awaited after the widget is dropped
+    pub fn finished(&self) -> impl Future<Output = ()> + Send + 'static {
+        let done = self
+            .tasks
+            .iter()
+            .map(|task| task.done.clone())
+            .collect::<Vec<_>>();
+        join_all(done).map(|_| ())
+    }
+}
+
+impl Drop for TaskGroup {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
@@ -1,43 +1,259 @@
+use std::sync::{Arc, RwLockReadGuard, RwLockWriteGuard};
+
 use async_object::{Keeper, Tag};
-use bindings::Windows::UI::Composition::{CompositionDrawingSurface, SpriteVisual};
+use bindings::{
+    Microsoft::Graphics::Canvas::{
+        Text::{CanvasTextFormat, CanvasTextLayout, CanvasWordWrapping},
+        UI::Composition::CanvasComposition,
+    },
+    Windows::{
+        Foundation::{
+            Numerics::{Vector2, Vector3},
+            Size,
+        },
+        Graphics::DirectX::{DirectXAlphaMode, DirectXPixelFormat},
+        UI::{Colors, Composition::SpriteVisual},
+    },
+};
+use futures::{Future, StreamExt};
 
-use crate::{FrameTag, SlotTag};
+use crate::{slot::SlotPlug, FrameTag, ReceiveSlotEvent, SlotTag, TaskGroup, Theme, ThemeColor};
 
-struct Text {
-    refs: TextRefs,
-    text: String,
-}
-
-#[derive(Clone)]
-struct TextRefs {
+pub struct Text {
     frame: FrameTag,
-    slot: SlotTag,
-    surface: Option<CompositionDrawingSurface>,
+    slot: SlotPlug,
     visual: SpriteVisual,
+    text: String,
+    font_size: f32,
+    /// Name of the theme's font. When set, it overrides `font_size`
+    font: Option<String>,
+    color: ThemeColor,
+    theme: Arc<Theme>,
+    slot_size: Vector2,
+    tasks: TaskGroup,
 }
 
-impl TextRefs {
-    fn new(frame: FrameTag, slot: SlotTag) -> crate::Result<Self> {
-        let shape = frame.compositor().CreateShapeVisual()?;
-        slot.container().Children()?.InsertAtTop(shape.clone())?;
-        shape.SetSize(slot.container().Size()?)?;
-        Ok(Self { frame, slot, shape })
+impl Text {
+    fn new(
+        frame: FrameTag,
+        slot: SlotTag,
+        text: String,
+        font_size: f32,
+        color: ThemeColor,
+    ) -> crate::Result<Self> {
+        let visual = frame.compositor()?.CreateSpriteVisual()?;
+        let theme = frame.theme()?;
+        let slot_size = slot.container()?.Size()?;
+        let slot = slot.plug("Text", visual.clone().into())?;
+        let tasks = TaskGroup::for_slot(frame.clone(), &slot.tag())?;
+        let mut text = Self {
+            frame,
+            slot,
+            visual,
+            text,
+            font_size,
+            font: None,
+            color,
+            theme,
+            slot_size,
+            tasks,
+        };
+        text.redraw()?;
+        Ok(text)
     }
-}
 
-#[derive(Clone)]
-struct TextKeeper {
-    keeper: Keeper<Text>,
+    fn set_text(&mut self, text: String) -> crate::Result<()> {
+        self.text = text;
+        self.redraw()
+    }
+
+    fn set_font_size(&mut self, font_size: f32) -> crate::Result<()> {
+        self.font_size = font_size;
+        self.redraw()
+    }
+
+    fn set_font(&mut self, font: Option<String>) -> crate::Result<()> {
+        self.font = font;
+        self.redraw()
+    }
+
+    fn set_color(&mut self, color: ThemeColor) -> crate::Result<()> {
+        self.color = color;
+        self.redraw()
+    }
+
+    fn set_theme(&mut self, theme: Arc<Theme>) -> crate::Result<()> {
+        self.theme = theme;
+        self.redraw()
+    }
+
+    fn set_size(&mut self, size: Vector2) -> crate::Result<()> {
+        tracing::trace!(size = ?size, "text size");
+        self.slot_size = size;
+        self.center()
+    }
+
+    /// Renders the text into a surface of exactly the text's size and reports this size
+    /// as desired size of the slot
+    fn redraw(&mut self) -> crate::Result<()> {
+        let canvas_device = self.frame.canvas_device()?;
+        let format = CanvasTextFormat::new()?;
+        match self.font.as_ref().and_then(|name| self.theme.font(name)) {
+            Some(font) => {
+                format.SetFontFamily(font.family.as_str())?;
+                format.SetFontSize(font.size)?;
+            }
+            None => format.SetFontSize(self.font_size)?,
+        }
+        format.SetWordWrapping(CanvasWordWrapping::NoWrap)?;
+        let layout = CanvasTextLayout::Create(
+            &canvas_device,
+            self.text.as_str(),
+            &format,
+            f32::MAX,
+            f32::MAX,
+        )?;
+        let bounds = layout.LayoutBounds()?;
+        let text_size = Vector2 {
+            X: bounds.Width.ceil(),
+            Y: bounds.Height.ceil(),
+        };
+        let surface = self.frame.graphics_device()?.CreateDrawingSurface(
+            Size {
+                Width: text_size.X.max(1.),
+                Height: text_size.Y.max(1.),
+            },
+            DirectXPixelFormat::B8G8R8A8UIntNormalized,
+            DirectXAlphaMode::Premultiplied,
+        )?;
+        let session = CanvasComposition::CreateDrawingSession(&surface)?;
+        session.Clear(Colors::Transparent()?)?;
+        session.DrawTextLayoutAtCoordsWithColor(
+            &layout,
+            -bounds.X,
+            -bounds.Y,
+            self.theme.resolve(&self.color),
+        )?;
+        session.Close()?;
+        let brush = self
+            .frame
+            .compositor()?
+            .CreateSurfaceBrushWithSurface(&surface)?;
+        self.visual.SetBrush(brush)?;
+        self.visual.SetSize(text_size)?;
+        self.center()?;
+        if self.slot.tag().set_desired_size(Some(text_size))? {
+            tracing::debug!(text = %self.text, desired_size = ?text_size, "text desired size changed");
+            self.frame.request_layout()?;
+        }
+        Ok(())
+    }
+
+    fn center(&self) -> crate::Result<()> {
+        let text_size = self.visual.Size()?;
+        self.visual.SetOffset(Vector3 {
+            X: ((self.slot_size.X - text_size.X) / 2.).round(),
+            Y: ((self.slot_size.Y - text_size.Y) / 2.).round(),
+            Z: 0.,
+        })?;
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
-struct TextTag {
-    tag: Tag<Text>,
-    refs: TextRefs,
+pub struct TextKeeper(Keeper<Text>);
+
+impl TextKeeper {
+    pub fn new(
+        frame: FrameTag,
+        slot: SlotTag,
+        text: impl Into<String>,
+        font_size: f32,
+        color: impl Into<ThemeColor>,
+    ) -> crate::Result<Self> {
+        let keeper = Keeper::new(Text::new(
+            frame,
+            slot,
+            text.into(),
+            font_size,
+            color.into(),
+        )?);
+        let keeper = Self(keeper);
+        keeper.spawn_event_handlers()?;
+        Ok(keeper)
+    }
+    pub fn tag(&self) -> TextTag {
+        TextTag(self.0.tag())
+    }
+    pub fn get(&self) -> RwLockReadGuard<'_, Text> {
+        self.0.get()
+    }
+    pub fn get_mut(&self) -> RwLockWriteGuard<'_, Text> {
+        self.0.get_mut()
+    }
+    /// Resolves when event handlers of the text are stopped, i.e. after all keepers are dropped
+    pub fn tasks_finished(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.get().tasks.finished()
+    }
+    fn spawn_event_handlers(&self) -> crate::Result<()> {
+        let tag = self.tag();
+        let slot = self.get().slot.tag();
+        {
+            let tag = tag.clone();
+            let slot = slot.clone();
+            self.get_mut().tasks.spawn("Text::on_size", async move {
+                while let Some(size) = slot.on_size().next().await {
+                    tag.set_size(size.0)?;
+                }
+                Ok(())
+            })?;
+        }
+        self.get_mut()
+            .tasks
+            .spawn("Text::on_theme_changed", async move {
+                while let Some(event) = slot.on_theme_changed().next().await {
+                    tag.set_theme(event.0)?;
+                }
+                Ok(())
+            })
+    }
 }
 
-impl PartialEq for TextTag {
-    fn eq(&self, other: &Self) -> bool {
-        self.tag == other.tag
+#[derive(Clone, PartialEq)]
+pub struct TextTag(Tag<Text>);
+
+impl TextTag {
+    pub fn text(&self) -> crate::Result<String> {
+        Ok(self.0.call(|v| v.text.clone())?)
+    }
+    pub fn set_text(&self, text: impl Into<String>) -> crate::Result<()> {
+        let text = text.into();
+        Ok(self.0.call_mut(|v| v.set_text(text))??)
+    }
+    pub fn font_size(&self) -> crate::Result<f32> {
+        Ok(self.0.call(|v| v.font_size)?)
+    }
+    pub fn set_font_size(&self, font_size: f32) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_font_size(font_size))??)
+    }
+    pub fn font(&self) -> crate::Result<Option<String>> {
+        Ok(self.0.call(|v| v.font.clone())?)
+    }
+    /// Uses family and size of the named font of the theme instead of `font_size`
+    pub fn set_font(&self, font: Option<String>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_font(font))??)
+    }
+    pub fn color(&self) -> crate::Result<ThemeColor> {
+        Ok(self.0.call(|v| v.color.clone())?)
+    }
+    pub fn set_color(&self, color: impl Into<ThemeColor>) -> crate::Result<()> {
+        let color = color.into();
+        Ok(self.0.call_mut(|v| v.set_color(color))??)
+    }
+    fn set_theme(&self, theme: Arc<Theme>) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_theme(theme))??)
+    }
+    pub fn set_size(&self, size: Vector2) -> crate::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_size(size))??)
     }
 }
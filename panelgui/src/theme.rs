@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+
+use bindings::Windows::{Foundation::Numerics::Vector2, UI::Color};
+
+/// Color used for names missing in the theme, bright enough to be noticed
+const MISSING_COLOR: Color = rgb(0xFF, 0x00, 0xFF);
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    argb(0xFF, r, g, b)
+}
+
+const fn argb(a: u8, r: u8, g: u8, b: u8) -> Color {
+    Color {
+        A: a,
+        R: r,
+        G: g,
+        B: b,
+    }
+}
+
+/// Parses "#RRGGBB" or "#AARRGGBB"
+pub(crate) fn parse_hex_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    let component = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    match hex.len() {
+        6 => Some(Color {
+            A: 255,
+            R: component(0)?,
+            G: component(2)?,
+            B: component(4)?,
+        }),
+        8 => Some(Color {
+            A: component(0)?,
+            R: component(2)?,
+            G: component(4)?,
+            B: component(6)?,
+        }),
+        _ => None,
+    }
+}
+
+/// Parses a hex color or one of the basic color names: "black", "white", "red", ...
+pub fn parse_color(value: &str) -> Option<Color> {
+    if value.starts_with('#') {
+        return parse_hex_color(value);
+    }
+    let color = match value.to_ascii_lowercase().as_str() {
+        "transparent" => Color {
+            A: 0,
+            R: 0xFF,
+            G: 0xFF,
+            B: 0xFF,
+        },
+        "black" => rgb(0x00, 0x00, 0x00),
+        "white" => rgb(0xFF, 0xFF, 0xFF),
+        "gray" | "grey" => rgb(0x80, 0x80, 0x80),
+        "silver" => rgb(0xC0, 0xC0, 0xC0),
+        "red" => rgb(0xFF, 0x00, 0x00),
+        "maroon" => rgb(0x80, 0x00, 0x00),
+        "orange" => rgb(0xFF, 0xA5, 0x00),
+        "yellow" => rgb(0xFF, 0xFF, 0x00),
+        "olive" => rgb(0x80, 0x80, 0x00),
+        "lime" => rgb(0x00, 0xFF, 0x00),
+        "green" => rgb(0x00, 0x80, 0x00),
+        "cyan" | "aqua" => rgb(0x00, 0xFF, 0xFF),
+        "teal" => rgb(0x00, 0x80, 0x80),
+        "blue" => rgb(0x00, 0x00, 0xFF),
+        "navy" => rgb(0x00, 0x00, 0x80),
+        "magenta" | "fuchsia" => rgb(0xFF, 0x00, 0xFF),
+        "purple" => rgb(0x80, 0x00, 0x80),
+        _ => return None,
+    };
+    Some(color)
+}
+
+/// Color given explicitly or by name in the frame's theme. Named colors follow theme changes
+#[derive(Clone, Debug, PartialEq)]
+pub enum ThemeColor {
+    Fixed(Color),
+    Named(String),
+}
+
+impl From<Color> for ThemeColor {
+    fn from(color: Color) -> Self {
+        ThemeColor::Fixed(color)
+    }
+}
+
+impl From<&str> for ThemeColor {
+    fn from(name: &str) -> Self {
+        ThemeColor::Named(name.to_string())
+    }
+}
+
+impl From<String> for ThemeColor {
+    fn from(name: String) -> Self {
+        ThemeColor::Named(name)
+    }
+}
+
+/// Colors of the game tile with some value
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TileStyle {
+    pub background: Color,
+    pub text: Color,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Font {
+    pub family: String,
+    pub size: f32,
+}
+
+impl Font {
+    pub fn new(family: impl Into<String>, size: f32) -> Self {
+        Self {
+            family: family.into(),
+            size,
+        }
+    }
+}
+
+/// Look of the widgets in the frame. Built-in themes define colors "background", "text",
+/// "accent", "focus", "marker", "primary", "secondary", "tertiary", "backdrop", "shadow",
+/// "inspector" and fonts "text", "title", "tile"
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub name: String,
+    pub colors: HashMap<String, Color>,
+    /// Radius of round corners relative to the smaller side of the widget
+    pub corner_radius: f32,
+    /// Tile styles by value: the first one is for 2, the second for 4 and so on.
+    /// Larger values use the last style
+    pub tiles: Vec<TileStyle>,
+    pub fonts: HashMap<String, Font>,
+}
+
+impl Theme {
+    fn with_colors(name: &str, colors: &[(&str, Color)], tiles: Vec<TileStyle>) -> Self {
+        let fonts = [
+            ("text", Font::new("Segoe UI", 16.)),
+            ("title", Font::new("Segoe UI", 32.)),
+            ("tile", Font::new("Segoe UI Semibold", 40.)),
+        ];
+        Self {
+            name: name.to_string(),
+            colors: colors
+                .iter()
+                .map(|(name, color)| (name.to_string(), *color))
+                .collect(),
+            corner_radius: 0.05,
+            tiles,
+            fonts: fonts
+                .iter()
+                .map(|(name, font)| (name.to_string(), font.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn light() -> Self {
+        let dark_text = rgb(0x77, 0x6E, 0x65);
+        let light_text = rgb(0xF9, 0xF6, 0xF2);
+        let tiles = [
+            (rgb(0xEE, 0xE4, 0xDA), dark_text),
+            (rgb(0xED, 0xE0, 0xC8), dark_text),
+            (rgb(0xF2, 0xB1, 0x79), light_text),
+            (rgb(0xF5, 0x95, 0x63), light_text),
+            (rgb(0xF6, 0x7C, 0x5F), light_text),
+            (rgb(0xF6, 0x5E, 0x3B), light_text),
+            (rgb(0xED, 0xCF, 0x72), light_text),
+            (rgb(0xED, 0xCC, 0x61), light_text),
+            (rgb(0xED, 0xC8, 0x50), light_text),
+            (rgb(0xED, 0xC5, 0x3F), light_text),
+            (rgb(0xED, 0xC2, 0x2E), light_text),
+        ];
+        Self::with_colors(
+            "light",
+            &[
+                ("background", rgb(0xFA, 0xF8, 0xEF)),
+                ("text", dark_text),
+                ("accent", rgb(0x8F, 0x7A, 0x66)),
+                ("focus", rgb(0x3C, 0x3A, 0x32)),
+                ("marker", rgb(0xFF, 0xD7, 0x00)),
+                ("primary", rgb(0xBB, 0xAD, 0xA0)),
+                ("secondary", rgb(0xCD, 0xC1, 0xB4)),
+                ("tertiary", rgb(0xBB, 0xAD, 0xA0)),
+                ("backdrop", argb(0x80, 0xFA, 0xF8, 0xEF)),
+                ("shadow", argb(0x60, 0x00, 0x00, 0x00)),
+                ("inspector", rgb(0xD0, 0x00, 0xD0)),
+            ],
+            tiles
+                .iter()
+                .map(|&(background, text)| TileStyle { background, text })
+                .collect(),
+        )
+    }
+
+    pub fn dark() -> Self {
+        let text = rgb(0xF0, 0xF0, 0xF0);
+        let tiles = [
+            rgb(0x3C, 0x4A, 0x5A),
+            rgb(0x44, 0x5A, 0x6E),
+            rgb(0x2E, 0x7D, 0x8C),
+            rgb(0x2A, 0x8F, 0x7A),
+            rgb(0x4B, 0x9B, 0x4F),
+            rgb(0x8A, 0x9A, 0x2E),
+            rgb(0xB8, 0x8A, 0x1E),
+            rgb(0xC0, 0x6A, 0x1A),
+            rgb(0xC0, 0x4A, 0x2A),
+            rgb(0xB0, 0x30, 0x4A),
+            rgb(0x9A, 0x2A, 0x7A),
+        ];
+        Self::with_colors(
+            "dark",
+            &[
+                ("background", rgb(0x1E, 0x1E, 0x24)),
+                ("text", text),
+                ("accent", rgb(0x5A, 0x9B, 0xD5)),
+                ("focus", rgb(0xF0, 0xF0, 0xF0)),
+                ("marker", rgb(0xFF, 0xC1, 0x07)),
+                ("primary", rgb(0x2D, 0x2D, 0x36)),
+                ("secondary", rgb(0x38, 0x38, 0x44)),
+                ("tertiary", rgb(0x2D, 0x2D, 0x36)),
+                ("backdrop", argb(0xA0, 0x00, 0x00, 0x00)),
+                ("shadow", argb(0xC0, 0x00, 0x00, 0x00)),
+                ("inspector", rgb(0xFF, 0x40, 0xFF)),
+            ],
+            tiles
+                .iter()
+                .map(|&background| TileStyle { background, text })
+                .collect(),
+        )
+    }
+
+    /// Theme with maximal contrast and square corners. Tile colors repeat,
+    /// so tiles are told apart by their text only
+    pub fn high_contrast() -> Self {
+        let black = rgb(0x00, 0x00, 0x00);
+        let white = rgb(0xFF, 0xFF, 0xFF);
+        let yellow = rgb(0xFF, 0xFF, 0x00);
+        let cyan = rgb(0x00, 0xFF, 0xFF);
+        let tiles = [
+            (white, black),
+            (yellow, black),
+            (cyan, black),
+            (black, white),
+            (black, yellow),
+            (black, cyan),
+        ];
+        let mut theme = Self::with_colors(
+            "high_contrast",
+            &[
+                ("background", black),
+                ("text", white),
+                ("accent", yellow),
+                ("focus", cyan),
+                ("marker", yellow),
+                ("primary", rgb(0x26, 0x26, 0x26)),
+                ("secondary", rgb(0x40, 0x40, 0x40)),
+                ("tertiary", rgb(0x26, 0x26, 0x26)),
+                ("backdrop", argb(0xC0, 0x00, 0x00, 0x00)),
+                // Shapes are told apart by contrast, not by shadows
+                ("shadow", argb(0x00, 0x00, 0x00, 0x00)),
+                ("inspector", cyan),
+            ],
+            tiles
+                .iter()
+                .map(|&(background, text)| TileStyle { background, text })
+                .collect(),
+        );
+        theme.corner_radius = 0.;
+        theme
+    }
+
+    /// Built-in theme by name: "light", "dark" or "high_contrast"
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "light" => Some(Self::light()),
+            "dark" => Some(Self::dark()),
+            "high_contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+
+    pub fn color(&self, name: &str) -> Option<Color> {
+        self.colors.get(name).copied()
+    }
+
+    /// Color for the widget. Names missing in the theme are shown in magenta
+    pub fn resolve(&self, color: &ThemeColor) -> Color {
+        match color {
+            ThemeColor::Fixed(color) => *color,
+            ThemeColor::Named(name) => self.color(name).unwrap_or_else(|| {
+                tracing::warn!(theme = %self.name, color = %name, "color missing in theme");
+                MISSING_COLOR
+            }),
+        }
+    }
+
+    pub fn corner_radius(&self, size: Vector2) -> f32 {
+        size.X.min(size.Y) * self.corner_radius
+    }
+
+    /// Style of the tile with value 2, 4, 8, ...
+    pub fn tile(&self, value: u32) -> Option<TileStyle> {
+        if value < 2 {
+            return None;
+        }
+        let index = (31 - value.leading_zeros()) as usize - 1;
+        self.tiles.get(index).or_else(|| self.tiles.last()).copied()
+    }
+
+    pub fn font(&self, name: &str) -> Option<&Font> {
+        self.fonts.get(name)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
@@ -0,0 +1,389 @@
+//! Themes in TOML files, editable without rebuilding the application:
+//!
+//! ```toml
+//! name = "sand"
+//! base = "light"
+//! corner_radius = 0.1
+//!
+//! [colors]
+//! background = "#FAF8EF"
+//! text = "black"
+//!
+//! [fonts]
+//! title = { family = "Segoe UI", size = 36 }
+//!
+//! [[tiles]]
+//! background = "#EEE4DA"
+//! text = "#776E65"
+//! ```
+//!
+//! Values missing in the file are taken from the `base` theme, "light" by default.
+//! `tiles`, if given, replace all tiles of the base theme
+
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use bindings::Windows::UI::Color;
+use futures::StreamExt;
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{theme::parse_color, tree::watch_file, Font, FrameTag, TaskGroup, Theme, TileStyle};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeSource {
+    name: Option<String>,
+    base: Option<Spanned<String>>,
+    corner_radius: Option<Spanned<f32>>,
+    #[serde(default)]
+    colors: BTreeMap<String, Spanned<String>>,
+    #[serde(default)]
+    fonts: BTreeMap<String, FontSource>,
+    tiles: Option<Vec<TileSource>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FontSource {
+    family: Option<Spanned<String>>,
+    size: Option<Spanned<f32>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TileSource {
+    background: Spanned<String>,
+    text: Spanned<String>,
+}
+
+/// Makes errors pointing to the place in the source
+struct Validator<'a> {
+    origin: &'a str,
+    source: &'a str,
+}
+
+impl<'a> Validator<'a> {
+    fn error(&self, offset: usize, key: &str, message: impl Display) -> crate::Error {
+        let (line, column) = position(self.source, token_start(self.source, offset));
+        crate::Error::ThemeFile {
+            origin: self.origin.to_string(),
+            line,
+            column,
+            message: format!("'{}': {}", key, message),
+        }
+    }
+
+    /// toml reports unknown fields at the start of their table. The error is moved
+    /// to the field itself, e.g. "'fonts.title.bold': unknown field, expected ..."
+    fn unknown_field(&self, message: &str, table_start: usize) -> Option<crate::Error> {
+        let (field, rest) = message.strip_prefix("unknown field `")?.split_once("`, ")?;
+        let (expected, key) = match rest.rsplit_once(" for key `") {
+            Some((expected, table)) => {
+                (expected, format!("{}.{}", table.strip_suffix('`')?, field))
+            }
+            None => (rest, field.to_string()),
+        };
+        let at = find_key(self.source, table_start, field)?;
+        Some(self.error(at, &key, format!("unknown field, {}", expected)))
+    }
+
+    fn color(&self, key: &str, value: &Spanned<String>) -> crate::Result<Color> {
+        parse_color(value.get_ref()).ok_or_else(|| {
+            self.error(
+                value.start(),
+                key,
+                format!(
+                    "bad color \"{}\", expected #RRGGBB, #AARRGGBB or a color name",
+                    value.get_ref()
+                ),
+            )
+        })
+    }
+
+    fn theme(&self, source: ThemeSource) -> crate::Result<Theme> {
+        let mut theme = match &source.base {
+            Some(base) => Theme::builtin(base.get_ref()).ok_or_else(|| {
+                self.error(
+                    base.start(),
+                    "base",
+                    format!(
+                        "unknown theme \"{}\", expected \"light\", \"dark\" or \"high_contrast\"",
+                        base.get_ref()
+                    ),
+                )
+            })?,
+            None => Theme::light(),
+        };
+        if let Some(name) = source.name {
+            theme.name = name;
+        }
+        if let Some(radius) = source.corner_radius {
+            let value = *radius.get_ref();
+            if !(0. ..=0.5).contains(&value) {
+                return Err(self.error(
+                    radius.start(),
+                    "corner_radius",
+                    format!("{} is out of range 0..0.5", value),
+                ));
+            }
+            theme.corner_radius = value;
+        }
+        for (name, value) in &source.colors {
+            let color = self.color(&format!("colors.{}", name), value)?;
+            theme.colors.insert(name.clone(), color);
+        }
+        for (name, font) in source.fonts {
+            let mut result = theme
+                .font(&name)
+                .cloned()
+                .unwrap_or_else(|| Font::new("Segoe UI", 16.));
+            if let Some(family) = font.family {
+                result.family = family.into_inner();
+            }
+            if let Some(size) = font.size {
+                let value = *size.get_ref();
+                // NaN passes `value <= 0.`, it's caught as not finite
+                if !value.is_finite() || value <= 0. {
+                    return Err(self.error(
+                        size.start(),
+                        &format!("fonts.{}.size", name),
+                        "font size must be a positive number",
+                    ));
+                }
+                result.size = size.into_inner();
+            }
+            theme.fonts.insert(name, result);
+        }
+        if let Some(tiles) = source.tiles {
+            theme.tiles = tiles
+                .iter()
+                .enumerate()
+                .map(|(i, tile)| {
+                    Ok(TileStyle {
+                        background: self
+                            .color(&format!("tiles[{}].background", i), &tile.background)?,
+                        text: self.color(&format!("tiles[{}].text", i), &tile.text)?,
+                    })
+                })
+                .collect::<crate::Result<_>>()?;
+        }
+        Ok(theme)
+    }
+}
+
+/// Start of the value at the byte offset. Spans of floats start after the decimal point
+fn token_start(source: &str, offset: usize) -> usize {
+    let before = &source[..offset.min(source.len())];
+    before
+        .rfind(|c: char| c.is_whitespace() || "={[,".contains(c))
+        .map_or(0, |i| i + 1)
+}
+
+/// Byte offset of the 0-based line and column
+fn offset(source: &str, line: usize, column: usize) -> usize {
+    let line_start = source
+        .split_inclusive('\n')
+        .take(line)
+        .map(str::len)
+        .sum::<usize>();
+    source[line_start..]
+        .char_indices()
+        .nth(column)
+        .map_or(source.len(), |(i, _)| line_start + i)
+}
+
+/// Offset of the first `key = ` at or after `from`, either on its own line or in an inline table
+fn find_key(source: &str, from: usize, key: &str) -> Option<usize> {
+    source[from..]
+        .match_indices(key)
+        .map(|(i, _)| from + i)
+        .find(|&i| {
+            let before = source[..i].trim_end_matches([' ', '\t']);
+            let after = source[i + key.len()..].trim_start_matches([' ', '\t']);
+            (before.is_empty() || before.ends_with(['\n', '{', ','])) && after.starts_with('=')
+        })
+}
+
+/// 1-based line and column of the byte offset
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count() + 1;
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+/// Parses the theme. `origin` is the file name used in error messages
+pub fn parse_theme(source: &str, origin: &str) -> crate::Result<Theme> {
+    let validator = Validator { origin, source };
+    let theme_source: ThemeSource = toml::from_str(source).map_err(|e| {
+        let (line, column) = e.line_col().map_or((0, 0), |(l, c)| (l + 1, c + 1));
+        // The position is already in the error, don't repeat it in the message
+        let mut message = e.to_string();
+        if let Some(index) = message.rfind(" at line ") {
+            message.truncate(index);
+        }
+        if let Some(error) = e
+            .line_col()
+            .and_then(|(l, c)| validator.unknown_field(&message, offset(source, l, c)))
+        {
+            return error;
+        }
+        crate::Error::ThemeFile {
+            origin: origin.to_string(),
+            line,
+            column,
+            message,
+        }
+    })?;
+    validator.theme(theme_source)
+}
+
+pub fn load_theme(path: &Path) -> crate::Result<Theme> {
+    let origin = path.display().to_string();
+    let source = std::fs::read_to_string(path).map_err(|source| crate::Error::Io {
+        path: origin.clone(),
+        source,
+    })?;
+    parse_theme(&source, &origin)
+}
+
+/// Applies the theme from the file to the frame and reapplies it each time the file changes.
+/// If the changed file is invalid, the error is logged and the current theme stays
+pub struct ThemeWatcher {
+    tasks: TaskGroup,
+}
+
+impl ThemeWatcher {
+    pub fn new(frame: FrameTag, path: impl Into<PathBuf>) -> crate::Result<Self> {
+        let path = path.into();
+        frame.set_theme(load_theme(&path)?)?;
+        let mut changes = watch_file(path.clone());
+        let mut tasks = TaskGroup::new(frame.clone());
+        tasks.spawn("ThemeWatcher::reload", async move {
+            while changes.next().await.is_some() {
+                match load_theme(&path) {
+                    Ok(theme) => frame.set_theme(theme)?,
+                    Err(error) => tracing::warn!(error = %error, "theme not reloaded"),
+                }
+            }
+            Ok(())
+        })?;
+        Ok(Self { tasks })
+    }
+
+    /// Stops watching the file, keeping the current theme
+    pub fn stop(&mut self) {
+        self.tasks.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_theme;
+    use crate::{parse_color, Font, Theme};
+
+    fn error(source: &str) -> String {
+        parse_theme(source, "theme.toml").unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_sand_theme() {
+        let theme = parse_theme(include_str!("../../themes/sand.toml"), "sand.toml").unwrap();
+        assert_eq!(theme.name, "sand");
+        assert_eq!(theme.corner_radius, 0.08);
+        assert_eq!(theme.color("marker"), parse_color("orange"));
+        assert_eq!(theme.color("background"), parse_color("#F4EBD9"));
+        // Colors missing in the file come from the base theme
+        assert_eq!(theme.color("focus"), Theme::light().color("focus"));
+        assert_eq!(theme.font("title"), Some(&Font::new("Georgia", 36.)));
+        assert_eq!(theme.font("tile"), Theme::light().font("tile"));
+        assert_eq!(theme.tiles.len(), 7);
+        assert_eq!(Some(theme.tiles[2].text), parse_color("white"));
+    }
+
+    #[test]
+    fn bad_colors() {
+        assert_eq!(
+            error("[colors]\nbackground = \"#12345\""),
+            "theme.toml:2:14: 'colors.background': bad color \"#12345\", \
+             expected #RRGGBB, #AARRGGBB or a color name"
+        );
+        assert_eq!(
+            error("[colors]\ntext = \"blak\""),
+            "theme.toml:2:8: 'colors.text': bad color \"blak\", \
+             expected #RRGGBB, #AARRGGBB or a color name"
+        );
+    }
+
+    #[test]
+    fn bad_tile_colors_name_the_tile() {
+        assert_eq!(
+            error(concat!(
+                "[[tiles]]\nbackground = \"white\"\ntext = \"black\"\n",
+                "[[tiles]]\nbackground = \"white\"\ntext = \"#GG0000\"\n",
+            )),
+            "theme.toml:6:8: 'tiles[1].text': bad color \"#GG0000\", \
+             expected #RRGGBB, #AARRGGBB or a color name"
+        );
+    }
+
+    #[test]
+    fn unknown_base() {
+        assert_eq!(
+            error("name = \"mine\"\nbase = \"sepia\""),
+            "theme.toml:2:8: 'base': unknown theme \"sepia\", \
+             expected \"light\", \"dark\" or \"high_contrast\""
+        );
+    }
+
+    #[test]
+    fn bad_corner_radius() {
+        // Spans of floats in toml start after the point, the column is of the whole number
+        assert_eq!(
+            error("corner_radius = 0.75"),
+            "theme.toml:1:17: 'corner_radius': 0.75 is out of range 0..0.5"
+        );
+        assert_eq!(
+            error("corner_radius = -1"),
+            "theme.toml:1:17: 'corner_radius': -1 is out of range 0..0.5"
+        );
+        assert_eq!(
+            error("corner_radius = nan"),
+            "theme.toml:1:17: 'corner_radius': NaN is out of range 0..0.5"
+        );
+    }
+
+    #[test]
+    fn bad_font_sizes() {
+        assert_eq!(
+            error("[fonts]\ntitle = { size = -3.5 }"),
+            "theme.toml:2:18: 'fonts.title.size': font size must be a positive number"
+        );
+        assert_eq!(
+            error("[fonts]\ntext = { size = 0 }"),
+            "theme.toml:2:17: 'fonts.text.size': font size must be a positive number"
+        );
+        assert_eq!(
+            error("[fonts.tile]\nfamily = \"Arial\"\nsize = nan"),
+            "theme.toml:3:8: 'fonts.tile.size': font size must be a positive number"
+        );
+    }
+
+    #[test]
+    fn unknown_fields_point_to_the_field() {
+        assert_eq!(
+            error("name = \"mine\"\ncorner_radus = 0.1"),
+            "theme.toml:2:1: 'corner_radus': unknown field, expected one of \
+             `name`, `base`, `corner_radius`, `colors`, `fonts`, `tiles`"
+        );
+        assert_eq!(
+            error("[fonts]\ntitle = { size = 30, bold = true }"),
+            "theme.toml:2:22: 'fonts.title.bold': unknown field, expected `family` or `size`"
+        );
+    }
+}
@@ -0,0 +1,1117 @@
+//! Declarative description of a widget tree. The description is usually loaded from a RON file,
+//! so the layout of a screen can be tweaked without rebuilding the application.
+//! The same description can be composed in code:
+//!
+//! ```ignore
+//! let tree = Tree::build(&frame, &slot, &[
+//!     BackgroundDesc::new("background").into(),
+//!     RibbonDesc::new(RibbonOrientation::Horizontal)
+//!         .spacing(10.)
+//!         .cell(CellDesc::new().with(BackgroundDesc::new("primary")))
+//!         .cell(CellDesc::auto().with(TextDesc::new("Score", 32., "text").named("score")))
+//!         .into(),
+//! ])?;
+//! let score = tree.text("score");
+//! ```
+
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, SystemTime},
+};
+
+use bindings::Windows::Foundation::Numerics::Vector2;
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    StreamExt,
+};
+use ron::{extensions::Extensions, Options};
+use serde::Deserialize;
+
+use crate::{
+    layout::{Alignment, Overflow, Rect, Thickness},
+    theme::parse_hex_color,
+    BackgroundKeeper, BackgroundTag, Bitmap, Border, CellLimit, FrameTag, Gradient, ImageFit,
+    ImageKeeper, ImageTag, NineGrid, RibbonKeeper, RibbonOrientation, RibbonTag, Shadow,
+    ShapeKeeper, ShapeTag, SlotTag, Stroke, TaskGroup, TextKeeper, TextTag, ThemeColor,
+};
+
+/// How often the watched description file is checked for modifications
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Color written as "#RRGGBB", "#AARRGGBB" or as a name of the theme's color
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ColorDesc(pub ThemeColor);
+
+impl TryFrom<String> for ColorDesc {
+    type Error = String;
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        if value.starts_with('#') {
+            parse_hex_color(&value)
+                .map(|color| ColorDesc(ThemeColor::Fixed(color)))
+                .ok_or_else(|| format!("bad color '{}', expected #RRGGBB or #AARRGGBB", value))
+        } else {
+            Ok(ColorDesc(ThemeColor::Named(value)))
+        }
+    }
+}
+
+fn default_font_size() -> f32 {
+    16.
+}
+
+/// Widget plugged into a slot. Widgets with a name can be found in the built `Tree`
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub enum WidgetDesc {
+    Background(BackgroundDesc),
+    Text(TextDesc),
+    Image(ImageDesc),
+    Shape(ShapeDesc),
+    Ribbon(RibbonDesc),
+}
+
+impl From<BackgroundDesc> for WidgetDesc {
+    fn from(desc: BackgroundDesc) -> Self {
+        WidgetDesc::Background(desc)
+    }
+}
+
+impl From<TextDesc> for WidgetDesc {
+    fn from(desc: TextDesc) -> Self {
+        WidgetDesc::Text(desc)
+    }
+}
+
+impl From<ImageDesc> for WidgetDesc {
+    fn from(desc: ImageDesc) -> Self {
+        WidgetDesc::Image(desc)
+    }
+}
+
+impl From<ShapeDesc> for WidgetDesc {
+    fn from(desc: ShapeDesc) -> Self {
+        WidgetDesc::Shape(desc)
+    }
+}
+
+impl From<RibbonDesc> for WidgetDesc {
+    fn from(desc: RibbonDesc) -> Self {
+        WidgetDesc::Ribbon(desc)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BorderDesc {
+    pub width: f32,
+    pub color: ColorDesc,
+}
+
+impl BorderDesc {
+    pub fn new(width: f32, color: impl Into<ThemeColor>) -> Self {
+        Self {
+            width,
+            color: ColorDesc(color.into()),
+        }
+    }
+}
+
+fn default_gradient_end() -> (f32, f32) {
+    (0., 1.)
+}
+
+/// Linear gradient, vertical by default. Points are relative to the size of the background
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GradientDesc {
+    #[serde(default)]
+    pub start: (f32, f32),
+    #[serde(default = "default_gradient_end")]
+    pub end: (f32, f32),
+    /// Colors at offsets from 0 (start) to 1 (end)
+    pub stops: Vec<(f32, ColorDesc)>,
+}
+
+impl GradientDesc {
+    pub fn new(start: (f32, f32), end: (f32, f32)) -> Self {
+        Self {
+            start,
+            end,
+            stops: Vec::new(),
+        }
+    }
+    pub fn stop(mut self, offset: f32, color: impl Into<ThemeColor>) -> Self {
+        self.stops.push((offset, ColorDesc(color.into())));
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NineGridDesc {
+    /// PNG or JPEG file. A relative path is resolved against the directory of the
+    /// description file by `load`, and against the working directory otherwise
+    pub path: PathBuf,
+    /// Edges of the image which are not stretched
+    pub insets: Thickness,
+}
+
+impl NineGridDesc {
+    pub fn new(path: impl Into<PathBuf>, insets: Thickness) -> Self {
+        Self {
+            path: path.into(),
+            insets,
+        }
+    }
+}
+
+fn default_blur_radius() -> f32 {
+    8.
+}
+
+fn default_opacity() -> f32 {
+    1.
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShadowDesc {
+    pub color: ColorDesc,
+    #[serde(default)]
+    pub offset: (f32, f32),
+    #[serde(default = "default_blur_radius")]
+    pub blur_radius: f32,
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+}
+
+impl ShadowDesc {
+    pub fn new(color: impl Into<ThemeColor>, offset: (f32, f32), blur_radius: f32) -> Self {
+        Self {
+            color: ColorDesc(color.into()),
+            offset,
+            blur_radius,
+            opacity: 1.,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackgroundDesc {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub color: ColorDesc,
+    #[serde(default)]
+    pub round_corners: bool,
+    #[serde(default)]
+    pub border: Option<BorderDesc>,
+    /// Drawn instead of `color`
+    #[serde(default)]
+    pub gradient: Option<GradientDesc>,
+    /// Drawn instead of `color` and `gradient`
+    #[serde(default)]
+    pub nine_grid: Option<NineGridDesc>,
+    #[serde(default)]
+    pub shadow: Option<ShadowDesc>,
+}
+
+impl BackgroundDesc {
+    pub fn new(color: impl Into<ThemeColor>) -> Self {
+        Self {
+            name: None,
+            color: ColorDesc(color.into()),
+            round_corners: false,
+            border: None,
+            gradient: None,
+            nine_grid: None,
+            shadow: None,
+        }
+    }
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+    pub fn round_corners(mut self, round_corners: bool) -> Self {
+        self.round_corners = round_corners;
+        self
+    }
+    pub fn border(mut self, border: BorderDesc) -> Self {
+        self.border = Some(border);
+        self
+    }
+    pub fn gradient(mut self, gradient: GradientDesc) -> Self {
+        self.gradient = Some(gradient);
+        self
+    }
+    pub fn nine_grid(mut self, nine_grid: NineGridDesc) -> Self {
+        self.nine_grid = Some(nine_grid);
+        self
+    }
+    pub fn shadow(mut self, shadow: ShadowDesc) -> Self {
+        self.shadow = Some(shadow);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextDesc {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub text: String,
+    #[serde(default = "default_font_size")]
+    pub font_size: f32,
+    /// Font of the theme, overrides `font_size`
+    #[serde(default)]
+    pub font: Option<String>,
+    pub color: ColorDesc,
+}
+
+impl TextDesc {
+    pub fn new(text: impl Into<String>, font_size: f32, color: impl Into<ThemeColor>) -> Self {
+        Self {
+            name: None,
+            text: text.into(),
+            font_size,
+            font: None,
+            color: ColorDesc(color.into()),
+        }
+    }
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+    pub fn font(mut self, font: impl Into<String>) -> Self {
+        self.font = Some(font.into());
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImageDesc {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// PNG or JPEG file, relative as in `NineGridDesc::path`
+    pub path: PathBuf,
+    #[serde(default)]
+    pub fit: ImageFit,
+}
+
+impl ImageDesc {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: None,
+            path: path.into(),
+            fit: ImageFit::default(),
+        }
+    }
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+    pub fn fit(mut self, fit: ImageFit) -> Self {
+        self.fit = fit;
+        self
+    }
+}
+
+/// Path written as a list of commands, e.g.
+/// `path: [MoveTo((0, 0)), LineTo((10, 0)), ArcTo(radius: (5, 5), to: (10, 10)), Close]`
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShapeDesc {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Named fully, `Path` here is the file path from std
+    pub path: crate::path::Path,
+    /// Area of the path's coordinates fitted into the slot as (x, y, width, height),
+    /// the bounds of the path by default
+    #[serde(default)]
+    pub view_box: Option<(f32, f32, f32, f32)>,
+    #[serde(default)]
+    pub fill: Option<ColorDesc>,
+    #[serde(default)]
+    pub stroke: Option<BorderDesc>,
+}
+
+impl ShapeDesc {
+    pub fn new(path: crate::path::Path) -> Self {
+        Self {
+            name: None,
+            path,
+            view_box: None,
+            fill: None,
+            stroke: None,
+        }
+    }
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+    pub fn view_box(mut self, view_box: (f32, f32, f32, f32)) -> Self {
+        self.view_box = Some(view_box);
+        self
+    }
+    pub fn fill(mut self, color: impl Into<ThemeColor>) -> Self {
+        self.fill = Some(ColorDesc(color.into()));
+        self
+    }
+    pub fn stroke(mut self, width: f32, color: impl Into<ThemeColor>) -> Self {
+        self.stroke = Some(BorderDesc::new(width, color));
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RibbonDesc {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub orientation: RibbonOrientation,
+    #[serde(default)]
+    pub overflow: Overflow,
+    #[serde(default)]
+    pub padding: Thickness,
+    #[serde(default)]
+    pub spacing: f32,
+    #[serde(default)]
+    pub cells: Vec<CellDesc>,
+}
+
+impl RibbonDesc {
+    pub fn new(orientation: RibbonOrientation) -> Self {
+        Self {
+            name: None,
+            orientation,
+            overflow: Overflow::default(),
+            padding: Thickness::default(),
+            spacing: 0.,
+            cells: Vec::new(),
+        }
+    }
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+    pub fn padding(mut self, padding: Thickness) -> Self {
+        self.padding = padding;
+        self
+    }
+    pub fn spacing(mut self, spacing: f32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+    pub fn cell(mut self, cell: impl Into<CellDesc>) -> Self {
+        self.cells.push(cell.into());
+        self
+    }
+}
+
+/// Cell of a ribbon: its `CellLimit` and widgets plugged into the cell's slot
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CellDesc {
+    /// Name to find the cell's slot in the built `Tree`, e.g. to plug a widget
+    /// which can't be described
+    pub name: Option<String>,
+    pub ratio: f32,
+    pub content_ratio: (f32, f32),
+    pub min_size: f32,
+    pub max_size: Option<f32>,
+    pub margin: Thickness,
+    pub align: Alignment,
+    pub aspect_ratio: Option<f32>,
+    pub auto_size: bool,
+    pub focusable: bool,
+    pub opacity: f32,
+    pub visible: bool,
+    /// Content drawn outside of the cell is cut off
+    pub clip: bool,
+    pub content: Vec<WidgetDesc>,
+}
+
+impl CellDesc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Cell sized by its content, see `CellLimit::auto`
+    pub fn auto() -> Self {
+        CellLimit::auto().into()
+    }
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+    pub fn focusable(mut self, focusable: bool) -> Self {
+        self.focusable = focusable;
+        self
+    }
+    pub fn opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+    pub fn clip(mut self, clip: bool) -> Self {
+        self.clip = clip;
+        self
+    }
+    pub fn with(mut self, widget: impl Into<WidgetDesc>) -> Self {
+        self.content.push(widget.into());
+        self
+    }
+    pub fn limit(&self) -> CellLimit {
+        let mut limit = CellLimit::new(
+            self.ratio,
+            Vector2 {
+                X: self.content_ratio.0,
+                Y: self.content_ratio.1,
+            },
+            self.min_size,
+            self.max_size,
+        );
+        limit.set_margin(self.margin);
+        limit.set_align(self.align);
+        limit.set_aspect_ratio(self.aspect_ratio);
+        limit.set_auto_size(self.auto_size);
+        limit
+    }
+}
+
+impl Default for CellDesc {
+    fn default() -> Self {
+        CellLimit::default().into()
+    }
+}
+
+impl From<CellLimit> for CellDesc {
+    fn from(limit: CellLimit) -> Self {
+        Self {
+            name: None,
+            ratio: limit.ratio,
+            content_ratio: (limit.content_ratio.X, limit.content_ratio.Y),
+            min_size: limit.min_size,
+            max_size: limit.max_size,
+            margin: limit.margin,
+            align: limit.align,
+            aspect_ratio: limit.aspect_ratio,
+            auto_size: limit.auto_size,
+            focusable: false,
+            opacity: 1.,
+            visible: true,
+            clip: false,
+            content: Vec::new(),
+        }
+    }
+}
+
+/// Format options: variants can be written as `Text(text: "a", ...)` instead of
+/// `Text((text: "a", ...))`, and `Some` can be omitted for optional fields
+fn ron_options() -> Options {
+    Options::default()
+        .with_default_extension(Extensions::UNWRAP_VARIANT_NEWTYPES | Extensions::IMPLICIT_SOME)
+}
+
+/// Parses the description of the widgets of one slot
+pub fn parse(source: &str) -> crate::Result<Vec<WidgetDesc>> {
+    ron_options()
+        .from_str(source)
+        .map_err(|e| crate::Error::Description(e.to_string()))
+}
+
+/// Loads the description file. Paths of images in it are made relative to its directory,
+/// so the description can be used from any working directory
+pub fn load(path: &Path) -> crate::Result<Vec<WidgetDesc>> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| crate::Error::Description(format!("{}: {}", path.display(), e)))?;
+    let mut widgets: Vec<WidgetDesc> = ron_options()
+        .from_str(&source)
+        .map_err(|e| crate::Error::Description(format!("{}: {}", path.display(), e)))?;
+    if let Some(dir) = path.parent() {
+        resolve_paths(&mut widgets, dir);
+    }
+    Ok(widgets)
+}
+
+/// Joins relative image paths to `dir`. Absolute paths are kept as is
+fn resolve_paths(widgets: &mut [WidgetDesc], dir: &Path) {
+    for widget in widgets {
+        match widget {
+            WidgetDesc::Background(desc) => {
+                if let Some(nine_grid) = &mut desc.nine_grid {
+                    nine_grid.path = dir.join(&nine_grid.path);
+                }
+            }
+            WidgetDesc::Image(desc) => desc.path = dir.join(&desc.path),
+            WidgetDesc::Ribbon(desc) => {
+                for cell in &mut desc.cells {
+                    resolve_paths(&mut cell.content, dir);
+                }
+            }
+            WidgetDesc::Text(_) | WidgetDesc::Shape(_) => {}
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum WidgetKeeper {
+    Background(BackgroundKeeper),
+    Ribbon(RibbonKeeper),
+    Text(TextKeeper),
+    Image(ImageKeeper),
+    Shape(ShapeKeeper),
+}
+
+/// Owner of all widgets created from a description. Dropping the tree removes the widgets
+#[derive(Default)]
+pub struct Tree {
+    keepers: Vec<WidgetKeeper>,
+    named: HashMap<String, WidgetKeeper>,
+    slots: HashMap<String, SlotTag>,
+}
+
+impl Tree {
+    pub fn build(frame: &FrameTag, slot: &SlotTag, widgets: &[WidgetDesc]) -> crate::Result<Self> {
+        let mut tree = Self::default();
+        for widget in widgets {
+            tree.build_widget(frame, slot, widget)?;
+        }
+        Ok(tree)
+    }
+
+    fn build_widget(
+        &mut self,
+        frame: &FrameTag,
+        slot: &SlotTag,
+        widget: &WidgetDesc,
+    ) -> crate::Result<()> {
+        match widget {
+            WidgetDesc::Background(desc) => {
+                let keeper = BackgroundKeeper::new(
+                    frame.clone(),
+                    slot.clone(),
+                    desc.color.0.clone(),
+                    desc.round_corners,
+                )?;
+                let background = keeper.tag();
+                if let Some(border) = &desc.border {
+                    background
+                        .set_border(Some(Border::new(border.width, border.color.0.clone())))?;
+                }
+                if let Some(gradient) = &desc.gradient {
+                    let mut value = Gradient::new(
+                        Vector2 {
+                            X: gradient.start.0,
+                            Y: gradient.start.1,
+                        },
+                        Vector2 {
+                            X: gradient.end.0,
+                            Y: gradient.end.1,
+                        },
+                    );
+                    for (offset, color) in &gradient.stops {
+                        value = value.stop(*offset, color.0.clone());
+                    }
+                    background.set_gradient(Some(value))?;
+                }
+                if let Some(nine_grid) = &desc.nine_grid {
+                    let bitmap = Bitmap::load(&nine_grid.path).map_err(|e| {
+                        crate::Error::Description(format!("{}: {}", nine_grid.path.display(), e))
+                    })?;
+                    background
+                        .set_nine_grid(Some(NineGrid::new(Arc::new(bitmap), nine_grid.insets)))?;
+                }
+                if let Some(shadow) = &desc.shadow {
+                    let mut value = Shadow::new(
+                        shadow.color.0.clone(),
+                        Vector2 {
+                            X: shadow.offset.0,
+                            Y: shadow.offset.1,
+                        },
+                        shadow.blur_radius,
+                    );
+                    value.opacity = shadow.opacity;
+                    background.set_shadow(Some(value))?;
+                }
+                self.push(WidgetKeeper::Background(keeper), desc.name.clone());
+            }
+            WidgetDesc::Text(desc) => {
+                let keeper = TextKeeper::new(
+                    frame.clone(),
+                    slot.clone(),
+                    desc.text.as_str(),
+                    desc.font_size,
+                    desc.color.0.clone(),
+                )?;
+                if desc.font.is_some() {
+                    keeper.tag().set_font(desc.font.clone())?;
+                }
+                self.push(WidgetKeeper::Text(keeper), desc.name.clone());
+            }
+            WidgetDesc::Image(desc) => {
+                let bitmap = Bitmap::load(&desc.path).map_err(|e| {
+                    crate::Error::Description(format!("{}: {}", desc.path.display(), e))
+                })?;
+                let keeper =
+                    ImageKeeper::new(frame.clone(), slot.clone(), Arc::new(bitmap), desc.fit)?;
+                self.push(WidgetKeeper::Image(keeper), desc.name.clone());
+            }
+            WidgetDesc::Shape(desc) => {
+                let keeper = ShapeKeeper::new(
+                    frame.clone(),
+                    slot.clone(),
+                    desc.path.clone(),
+                    desc.fill.as_ref().map(|fill| fill.0.clone()),
+                    desc.stroke
+                        .as_ref()
+                        .map(|stroke| Stroke::new(stroke.width, stroke.color.0.clone())),
+                )?;
+                if let Some((x, y, width, height)) = desc.view_box {
+                    keeper
+                        .tag()
+                        .set_view_box(Some(Rect::new(x, y, width, height)))?;
+                }
+                self.push(WidgetKeeper::Shape(keeper), desc.name.clone());
+            }
+            WidgetDesc::Ribbon(desc) => {
+                let keeper = RibbonKeeper::new(frame.clone(), slot.clone(), desc.orientation)?;
+                let ribbon = keeper.tag();
+                ribbon.set_overflow(desc.overflow)?;
+                ribbon.set_padding(desc.padding)?;
+                ribbon.set_spacing(desc.spacing)?;
+                self.push(WidgetKeeper::Ribbon(keeper), desc.name.clone());
+                for cell in &desc.cells {
+                    let cell_slot = ribbon.add_cell(cell.limit())?;
+                    cell_slot.set_focusable(cell.focusable)?;
+                    cell_slot.set_opacity(cell.opacity)?;
+                    cell_slot.set_visible(cell.visible)?;
+                    cell_slot.set_clip_to_bounds(cell.clip)?;
+                    if let Some(name) = &cell.name {
+                        self.slots.insert(name.clone(), cell_slot.clone());
+                    }
+                    for widget in &cell.content {
+                        self.build_widget(frame, &cell_slot, widget)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes ownership of the widget. The name, if any, replaces the previous widget with
+    /// the same name in lookups
+    pub fn push(&mut self, keeper: WidgetKeeper, name: Option<String>) {
+        if let Some(name) = name {
+            self.named.insert(name, keeper.clone());
+        }
+        self.keepers.push(keeper);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&WidgetKeeper> {
+        self.named.get(name)
+    }
+    /// Slot of the named cell
+    pub fn slot(&self, name: &str) -> Option<SlotTag> {
+        self.slots.get(name).cloned()
+    }
+    pub fn background(&self, name: &str) -> Option<BackgroundTag> {
+        match self.get(name)? {
+            WidgetKeeper::Background(keeper) => Some(keeper.tag()),
+            _ => None,
+        }
+    }
+    pub fn ribbon(&self, name: &str) -> Option<RibbonTag> {
+        match self.get(name)? {
+            WidgetKeeper::Ribbon(keeper) => Some(keeper.tag()),
+            _ => None,
+        }
+    }
+    pub fn text(&self, name: &str) -> Option<TextTag> {
+        match self.get(name)? {
+            WidgetKeeper::Text(keeper) => Some(keeper.tag()),
+            _ => None,
+        }
+    }
+    pub fn image(&self, name: &str) -> Option<ImageTag> {
+        match self.get(name)? {
+            WidgetKeeper::Image(keeper) => Some(keeper.tag()),
+            _ => None,
+        }
+    }
+    pub fn shape(&self, name: &str) -> Option<ShapeTag> {
+        match self.get(name)? {
+            WidgetKeeper::Shape(keeper) => Some(keeper.tag()),
+            _ => None,
+        }
+    }
+}
+
+/// Tree built from a description file which is rebuilt each time the file changes.
+/// If the changed file can't be loaded, the error is logged and the old tree stays
+pub struct TreeWatcher {
+    tree: Arc<Mutex<Tree>>,
+    reload_senders: Arc<Mutex<Vec<UnboundedSender<()>>>>,
+    tasks: TaskGroup,
+}
+
+impl TreeWatcher {
+    pub fn new(frame: FrameTag, slot: SlotTag, path: impl Into<PathBuf>) -> crate::Result<Self> {
+        let path = path.into();
+        let tree = Tree::build(&frame, &slot, &load(&path)?)?;
+        frame.request_layout()?;
+        let tree = Arc::new(Mutex::new(tree));
+        let mut changes = watch_file(path.clone());
+        let reload_senders: Arc<Mutex<Vec<UnboundedSender<()>>>> = Default::default();
+        let mut tasks = TaskGroup::new(frame.clone());
+        tasks.spawn("TreeWatcher::reload", {
+            let tree = tree.clone();
+            let reload_senders = reload_senders.clone();
+            async move {
+                while changes.next().await.is_some() {
+                    // The new tree is built next to the old one, so the old one stays
+                    // if the description is broken
+                    let built =
+                        load(&path).and_then(|widgets| Tree::build(&frame, &slot, &widgets));
+                    match built {
+                        Ok(built) => {
+                            *tree.lock().unwrap() = built;
+                            frame.request_layout()?;
+                            tracing::info!(path = %path.display(), "description reloaded");
+                            reload_senders
+                                .lock()
+                                .unwrap()
+                                .retain(|sender| sender.unbounded_send(()).is_ok());
+                        }
+                        Err(error) => {
+                            tracing::warn!(error = %error, "description not reloaded");
+                        }
+                    }
+                }
+                Ok(())
+            }
+        })?;
+        Ok(Self {
+            tree,
+            reload_senders,
+            tasks,
+        })
+    }
+
+    /// The current tree. Tags taken from it become invalid after the next reload
+    pub fn tree(&self) -> MutexGuard<'_, Tree> {
+        self.tree.lock().unwrap()
+    }
+
+    /// Notifies after the tree is replaced by a reloaded one, so that widgets plugged
+    /// into its slots from code can be plugged into the new slots
+    pub fn on_reload(&self) -> UnboundedReceiver<()> {
+        let (sender, receiver) = unbounded();
+        self.reload_senders.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Stops watching the file, keeping the current tree
+    pub fn stop(&mut self) {
+        self.tasks.cancel();
+    }
+}
+
+/// Polls the modification time of the file on a separate thread. The thread stops
+/// when the receiver is dropped
+pub(crate) fn watch_file(path: PathBuf) -> UnboundedReceiver<()> {
+    let (sender, receiver) = unbounded();
+    std::thread::spawn(move || {
+        let modified = |path: &Path| -> Option<SystemTime> {
+            std::fs::metadata(path).and_then(|m| m.modified()).ok()
+        };
+        let mut last = modified(&path);
+        while !sender.is_closed() {
+            std::thread::sleep(WATCH_INTERVAL);
+            let current = modified(&path);
+            if current != last {
+                last = current;
+                if sender.unbounded_send(()).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use bindings::Windows::UI::Color;
+
+    use std::path::PathBuf;
+
+    use super::{
+        parse, resolve_paths, BackgroundDesc, BorderDesc, CellDesc, ColorDesc, GradientDesc,
+        ImageDesc, NineGridDesc, RibbonDesc, ShadowDesc, ShapeDesc, TextDesc, WidgetDesc,
+    };
+    use crate::{
+        layout::{Alignment, Overflow, Thickness},
+        path::Path,
+        ImageFit, RibbonOrientation, ThemeColor,
+    };
+
+    fn ribbon(widget: &WidgetDesc) -> &RibbonDesc {
+        match widget {
+            WidgetDesc::Ribbon(desc) => desc,
+            other => panic!("expected ribbon, got {:?}", other),
+        }
+    }
+
+    fn parse_error(source: &str) -> String {
+        match parse(source) {
+            Err(crate::Error::Description(message)) => message,
+            other => panic!("expected description error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_main_screen() {
+        let widgets = parse(include_str!("../../ui/main.ron")).unwrap();
+        assert_eq!(widgets.len(), 2);
+        let screen = ribbon(&widgets[1]);
+        assert_eq!(screen.orientation, RibbonOrientation::Vertical);
+        match &screen.cells[0].content[..] {
+            [WidgetDesc::Text(text)] => {
+                assert_eq!(text.name.as_deref(), Some("score"));
+                assert_eq!(text.font.as_deref(), Some("title"));
+                assert_eq!(text.font_size, 16.);
+            }
+            other => panic!("expected score text, got {:?}", other),
+        }
+        let board = ribbon(&screen.cells[1].content[0])
+            .cells
+            .iter()
+            .find(|cell| cell.name.as_deref() == Some("board"))
+            .unwrap();
+        assert_eq!(board.ratio, 2.);
+        assert_eq!(board.min_size, 300.);
+        assert_eq!(board.aspect_ratio, Some(1.));
+        assert!(board.focusable && board.clip && board.content.is_empty());
+    }
+
+    #[test]
+    fn variants_and_options_are_unwrapped() {
+        // `Text(text: ..)` instead of `Text((text: ..))` and `"title"` instead of `Some("title")`
+        let implicit = parse(r#"[Text(name: "label", text: "a", font: "title", color: "text")]"#);
+        let explicit =
+            parse(r#"[Text(name: Some("label"), text: "a", font: Some("title"), color: "text")]"#);
+        match &implicit.as_ref().unwrap()[..] {
+            [WidgetDesc::Text(text)] => assert_eq!(text.font.as_deref(), Some("title")),
+            other => panic!("expected text, got {:?}", other),
+        }
+        assert_eq!(implicit.unwrap(), explicit.unwrap());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let message = parse_error(r#"[Background(color: "primary", colour: "secondary")]"#);
+        assert!(message.contains("colour"), "{}", message);
+        let message = parse_error(r#"[Ribbon(orientation: Stack, cells: [(ratoi: 2.0)])]"#);
+        assert!(message.contains("ratoi"), "{}", message);
+    }
+
+    #[test]
+    fn colors_are_hex_or_names() {
+        let color = |value: &str| parse(&format!("[Background(color: {:?})]", value));
+        let fixed = |a, r, g, b| {
+            vec![WidgetDesc::Background(BackgroundDesc::new(Color {
+                A: a,
+                R: r,
+                G: g,
+                B: b,
+            }))]
+        };
+        assert_eq!(color("#FF8000").unwrap(), fixed(0xFF, 0xFF, 0x80, 0x00));
+        assert_eq!(color("#80FF8000").unwrap(), fixed(0x80, 0xFF, 0x80, 0x00));
+        assert_eq!(
+            color("primary").unwrap(),
+            vec![WidgetDesc::Background(BackgroundDesc::new("primary"))]
+        );
+        let message = parse_error(r##"[Background(color: "#12")]"##);
+        assert!(message.contains("bad color '#12'"), "{}", message);
+        assert_eq!(
+            ColorDesc(ThemeColor::Named("text".to_string())),
+            ColorDesc(ThemeColor::from("text"))
+        );
+    }
+
+    #[test]
+    fn omitted_cell_fields_have_defaults() {
+        let widgets = parse("[Ribbon(orientation: Horizontal, cells: [()])]").unwrap();
+        let cell = &ribbon(&widgets[0]).cells[0];
+        assert_eq!(*cell, CellDesc::new());
+        assert_eq!(cell.opacity, 1.);
+        assert!(cell.visible);
+        assert_eq!(cell.ratio, 1.);
+        assert_eq!(cell.content_ratio, (1., 1.));
+        assert_eq!(cell.align, Alignment::Stretch);
+        assert!(!cell.auto_size && !cell.focusable && !cell.clip);
+    }
+
+    #[test]
+    fn builder_matches_description() {
+        let built = RibbonDesc::new(RibbonOrientation::Horizontal)
+            .named("bar")
+            .overflow(Overflow::Shrink)
+            .padding(Thickness::uniform(4.))
+            .spacing(10.)
+            .cell(
+                CellDesc::auto().with(
+                    TextDesc::new("Score", 32., "text")
+                        .named("score")
+                        .font("title"),
+                ),
+            )
+            .cell(
+                CellDesc::new()
+                    .named("icon")
+                    .focusable(true)
+                    .clip(true)
+                    .opacity(0.5)
+                    .with(BackgroundDesc::new("primary").round_corners(true))
+                    .with(ImageDesc::new("icon.png").fit(ImageFit::Fill)),
+            )
+            .cell(
+                CellDesc::new().visible(false).with(
+                    ShapeDesc::new(Path::ellipse((1., 1.), (1., 1.)))
+                        .view_box((0., 0., 2., 2.))
+                        .fill("marker")
+                        .stroke(2., "text"),
+                ),
+            );
+        let parsed = parse(
+            r#"[Ribbon(
+                name: "bar",
+                orientation: Horizontal,
+                overflow: Shrink,
+                padding: (left: 4.0, top: 4.0, right: 4.0, bottom: 4.0),
+                spacing: 10.0,
+                cells: [
+                    (auto_size: true, content: [
+                        Text(name: "score", text: "Score", font_size: 32.0, color: "text", font: "title"),
+                    ]),
+                    (name: "icon", focusable: true, clip: true, opacity: 0.5, content: [
+                        Background(color: "primary", round_corners: true),
+                        Image(path: "icon.png", fit: Fill),
+                    ]),
+                    (visible: false, content: [
+                        Shape(
+                            path: [Ellipse(center: (1.0, 1.0), radius: (1.0, 1.0))],
+                            view_box: (0.0, 0.0, 2.0, 2.0),
+                            fill: "marker",
+                            stroke: (width: 2.0, color: "text"),
+                        ),
+                    ]),
+                ],
+            )]"#,
+        )
+        .unwrap();
+        assert_eq!(parsed, vec![WidgetDesc::from(built)]);
+    }
+
+    #[test]
+    fn paths_are_resolved_against_the_description() {
+        let dir = PathBuf::from("ui");
+        let absolute = std::env::temp_dir().join("tile.png");
+        assert!(absolute.is_absolute());
+        let mut widgets =
+            vec![
+                WidgetDesc::from(ImageDesc::new("tile.png")),
+                WidgetDesc::from(ImageDesc::new(&absolute)),
+                WidgetDesc::from(RibbonDesc::new(RibbonOrientation::Stack).cell(
+                    CellDesc::new().with(BackgroundDesc::new("primary").nine_grid(
+                        NineGridDesc::new("frames/panel.png", Thickness::uniform(8.)),
+                    )),
+                )),
+            ];
+        resolve_paths(&mut widgets, &dir);
+        let image_path = |widget: &WidgetDesc| match widget {
+            WidgetDesc::Image(desc) => desc.path.clone(),
+            other => panic!("expected image, got {:?}", other),
+        };
+        assert_eq!(image_path(&widgets[0]), dir.join("tile.png"));
+        assert_eq!(image_path(&widgets[1]), absolute);
+        match &ribbon(&widgets[2]).cells[0].content[..] {
+            [WidgetDesc::Background(desc)] => assert_eq!(
+                desc.nine_grid.as_ref().unwrap().path,
+                dir.join("frames").join("panel.png")
+            ),
+            other => panic!("expected background, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_background_decorations() {
+        let parsed = parse(
+            r##"[Background(
+                color: "primary",
+                border: (width: 2.0, color: "accent"),
+                gradient: (start: (0.0, 0.0), end: (1.0, 0.0), stops: [(0.0, "primary"), (1.0, "#000000")]),
+                nine_grid: (path: "frame.png", insets: (left: 4.0, top: 4.0, right: 4.0, bottom: 4.0)),
+                shadow: (color: "shadow", offset: (0.0, 4.0), blur_radius: 16.0, opacity: 0.5),
+            )]"##,
+        )
+        .unwrap();
+        let mut shadow = ShadowDesc::new("shadow", (0., 4.), 16.);
+        shadow.opacity = 0.5;
+        let built = BackgroundDesc::new("primary")
+            .border(BorderDesc::new(2., "accent"))
+            .gradient(
+                GradientDesc::new((0., 0.), (1., 0.))
+                    .stop(0., "primary")
+                    .stop(
+                        1.,
+                        Color {
+                            A: 0xFF,
+                            R: 0,
+                            G: 0,
+                            B: 0,
+                        },
+                    ),
+            )
+            .nine_grid(NineGridDesc::new("frame.png", Thickness::uniform(4.)))
+            .shadow(shadow);
+        assert_eq!(parsed, vec![WidgetDesc::from(built)]);
+    }
+
+    #[test]
+    fn background_decorations_have_defaults() {
+        let parsed = parse(
+            r#"[Background(
+                color: "primary",
+                gradient: (stops: [(0.0, "primary"), (1.0, "secondary")]),
+                shadow: (color: "shadow"),
+            )]"#,
+        )
+        .unwrap();
+        let built = BackgroundDesc::new("primary")
+            .gradient(
+                GradientDesc::new((0., 0.), (0., 1.))
+                    .stop(0., "primary")
+                    .stop(1., "secondary"),
+            )
+            .shadow(ShadowDesc::new("shadow", (0., 0.), 8.));
+        assert_eq!(parsed, vec![WidgetDesc::from(built)]);
+        // Width and color of a border, the path and insets of a nine-grid are required
+        let message = parse_error(r#"[Background(color: "primary", border: (width: 2.0))]"#);
+        assert!(message.contains("color"), "{}", message);
+        let message = parse_error(r#"[Background(color: "primary", nine_grid: (path: "a.png"))]"#);
+        assert!(message.contains("insets"), "{}", message);
+    }
+}
@@ -0,0 +1,499 @@
+use std::sync::{Arc, RwLockReadGuard, RwLockWriteGuard};
+
+use async_object::{Keeper, Tag};
+use bindings::Windows::{
+    Foundation::Numerics::{Vector2, Vector3},
+    UI::Composition::ContainerVisual,
+};
+use futures::{Future, StreamExt};
+use panelgui::{
+    animation::{AnimationId, Easing, Transition},
+    AnimationTarget, AnimationValue, BackgroundKeeper, FrameTag, ReceiveSlotEvent, SendSlotEvent,
+    SlotKeeper, SlotPlug, SlotSize, SlotTag, TaskGroup, TextKeeper, Theme, ThemeChanged,
+    ThemeColor,
+};
+
+use crate::game::{MoveDiff, Position, TileChange};
+
+/// Gap between cells relative to the cell size
+const GAP: f32 = 0.12;
+
+const SLIDE: Transition = Transition {
+    duration: 0.1,
+    delay: 0.,
+    easing: Easing::EaseOut,
+};
+
+/// Merged tiles pop up when the sliding tiles reach them
+const POP: Transition = Transition {
+    duration: 0.2,
+    delay: 0.1,
+    easing: Easing::EaseOutBack,
+};
+
+/// Spawned tiles appear when sliding is over
+const FADE_IN: Transition = Transition {
+    duration: 0.2,
+    delay: 0.1,
+    easing: Easing::Linear,
+};
+
+const HIDDEN: Vector3 = Vector3 {
+    X: 0.,
+    Y: 0.,
+    Z: 1.,
+};
+
+const VISIBLE: Vector3 = Vector3 {
+    X: 1.,
+    Y: 1.,
+    Z: 1.,
+};
+
+/// Rounded rectangle in its own slot: the board's grid, an empty cell or a tile
+struct Piece {
+    parent: ContainerVisual,
+    parent_slot: SlotTag,
+    container: ContainerVisual,
+    slot_keeper: SlotKeeper,
+    background: BackgroundKeeper,
+}
+
+impl Piece {
+    fn new(
+        frame: &FrameTag,
+        parent: &ContainerVisual,
+        parent_slot: &SlotTag,
+        color: ThemeColor,
+    ) -> panelgui::Result<Self> {
+        let container = frame.compositor()?.CreateContainerVisual()?;
+        parent.Children()?.InsertAtTop(container.clone())?;
+        let slot_keeper = SlotKeeper::new(container.clone())?;
+        parent_slot.add_child(slot_keeper.tag())?;
+        let background = BackgroundKeeper::new(frame.clone(), slot_keeper.tag(), color, true)?;
+        Ok(Self {
+            parent: parent.clone(),
+            parent_slot: parent_slot.clone(),
+            container,
+            slot_keeper,
+            background,
+        })
+    }
+
+    fn place(&mut self, offset: Vector3, size: Vector2) -> panelgui::Result<()> {
+        self.container.SetOffset(offset)?;
+        // Tiles pop up from their centers
+        self.container.SetCenterPoint(Vector3 {
+            X: size.X / 2.,
+            Y: size.Y / 2.,
+            Z: 0.,
+        })?;
+        self.slot_keeper.send_size(SlotSize(size))
+    }
+}
+
+impl Drop for Piece {
+    fn drop(&mut self) {
+        let _ = self.parent.Children().map(|c| c.Remove(&self.container));
+        let _ = self.parent_slot.remove_child(&self.slot_keeper.tag());
+    }
+}
+
+struct Tile {
+    piece: Piece,
+    text: TextKeeper,
+    position: Position,
+    value: u32,
+}
+
+impl Tile {
+    fn new(
+        frame: &FrameTag,
+        parent: &ContainerVisual,
+        parent_slot: &SlotTag,
+        theme: &Theme,
+        position: Position,
+        value: u32,
+    ) -> panelgui::Result<Self> {
+        let (background, text_color) = tile_colors(theme, value);
+        let piece = Piece::new(frame, parent, parent_slot, background)?;
+        let text = TextKeeper::new(
+            frame.clone(),
+            piece.slot_keeper.tag(),
+            value.to_string(),
+            40.,
+            text_color,
+        )?;
+        text.tag().set_font(Some("tile".to_string()))?;
+        Ok(Self {
+            piece,
+            text,
+            position,
+            value,
+        })
+    }
+
+    fn set_theme(&self, theme: &Theme) -> panelgui::Result<()> {
+        let (background, text) = tile_colors(theme, self.value);
+        self.piece.background.tag().set_color(background)?;
+        self.text.tag().set_color(text)
+    }
+}
+
+fn tile_colors(theme: &Theme, value: u32) -> (ThemeColor, ThemeColor) {
+    match theme.tile(value) {
+        Some(style) => (style.background.into(), style.text.into()),
+        None => ("accent".into(), "text".into()),
+    }
+}
+
+/// Position and size of the board's grid inside the slot
+#[derive(Copy, Clone, Debug)]
+struct Geometry {
+    origin: Vector2,
+    cell: f32,
+    gap: f32,
+}
+
+impl Geometry {
+    fn new(size: Vector2, width: usize, height: usize) -> Self {
+        let (w, h) = (width as f32, height as f32);
+        let cell = (size.X / (w + (w + 1.) * GAP))
+            .min(size.Y / (h + (h + 1.) * GAP))
+            .max(0.);
+        let gap = cell * GAP;
+        let grid = Vector2 {
+            X: w * cell + (w + 1.) * gap,
+            Y: h * cell + (h + 1.) * gap,
+        };
+        Self {
+            origin: Vector2 {
+                X: ((size.X - grid.X) / 2.).round(),
+                Y: ((size.Y - grid.Y) / 2.).round(),
+            },
+            cell,
+            gap,
+        }
+    }
+
+    fn grid_size(&self, width: usize, height: usize) -> Vector2 {
+        Vector2 {
+            X: width as f32 * (self.cell + self.gap) + self.gap,
+            Y: height as f32 * (self.cell + self.gap) + self.gap,
+        }
+    }
+
+    fn cell_offset(&self, position: Position) -> Vector3 {
+        Vector3 {
+            X: self.origin.X + self.gap + position.x as f32 * (self.cell + self.gap),
+            Y: self.origin.Y + self.gap + position.y as f32 * (self.cell + self.gap),
+            Z: 0.,
+        }
+    }
+
+    fn cell_size(&self) -> Vector2 {
+        Vector2 {
+            X: self.cell,
+            Y: self.cell,
+        }
+    }
+}
+
+/// Game board. Tiles are not taken from the game directly: the board follows the game
+/// by `MoveDiff`s and animates them
+pub struct Board {
+    frame: FrameTag,
+    slot: SlotPlug,
+    container: ContainerVisual,
+    width: usize,
+    height: usize,
+    geometry: Geometry,
+    theme: Arc<Theme>,
+    grid: Piece,
+    cells: Vec<Piece>,
+    tiles: Vec<Tile>,
+    /// Tiles which were merged into others. They slide under the merged tile
+    /// and are removed on the next move
+    merged: Vec<Tile>,
+    /// Animations of the last move
+    animations: Vec<AnimationId>,
+    tasks: TaskGroup,
+}
+
+impl Board {
+    fn new(frame: FrameTag, slot: SlotTag, width: usize, height: usize) -> panelgui::Result<Self> {
+        let container = frame.compositor()?.CreateContainerVisual()?;
+        let theme = frame.theme()?;
+        let slot = slot.plug("Board", container.clone().into())?;
+        let grid = Piece::new(&frame, &container, &slot.tag(), "primary".into())?;
+        let cells = (0..width * height)
+            .map(|_| Piece::new(&frame, &container, &slot.tag(), "secondary".into()))
+            .collect::<panelgui::Result<_>>()?;
+        let tasks = TaskGroup::for_slot(frame.clone(), &slot.tag())?;
+        let mut board = Self {
+            frame,
+            slot,
+            container,
+            width,
+            height,
+            geometry: Geometry::new(Vector2 { X: 0., Y: 0. }, width, height),
+            theme,
+            grid,
+            cells,
+            tiles: Vec::new(),
+            merged: Vec::new(),
+            animations: Vec::new(),
+            tasks,
+        };
+        board.set_size(board.container.Size()?)?;
+        Ok(board)
+    }
+
+    fn set_size(&mut self, size: Vector2) -> panelgui::Result<()> {
+        tracing::trace!(size = ?size, "board size");
+        self.container.SetSize(size)?;
+        self.geometry = Geometry::new(size, self.width, self.height);
+        // Running animations would move tiles to the positions for the old size
+        self.fast_forward()?;
+        let geometry = self.geometry;
+        self.grid.place(
+            Vector3 {
+                X: geometry.origin.X,
+                Y: geometry.origin.Y,
+                Z: 0.,
+            },
+            geometry.grid_size(self.width, self.height),
+        )?;
+        for (index, cell) in self.cells.iter_mut().enumerate() {
+            let position = Position::new(index % self.width, index / self.width);
+            cell.place(geometry.cell_offset(position), geometry.cell_size())?;
+        }
+        for tile in self.tiles.iter_mut().chain(self.merged.iter_mut()) {
+            tile.piece
+                .place(geometry.cell_offset(tile.position), geometry.cell_size())?;
+        }
+        Ok(())
+    }
+
+    fn set_theme(&mut self, event: ThemeChanged) -> panelgui::Result<()> {
+        self.theme = event.0.clone();
+        let pieces = std::iter::once(&mut self.grid).chain(self.cells.iter_mut());
+        for piece in pieces {
+            piece.slot_keeper.send_theme_changed(event.clone())?;
+        }
+        for tile in self.tiles.iter_mut().chain(self.merged.iter_mut()) {
+            tile.piece.slot_keeper.send_theme_changed(event.clone())?;
+            tile.set_theme(&self.theme)?;
+        }
+        Ok(())
+    }
+
+    fn add_tile(&mut self, position: Position, value: u32) -> panelgui::Result<&mut Tile> {
+        let mut tile = Tile::new(
+            &self.frame,
+            &self.container,
+            &self.slot.tag(),
+            &self.theme,
+            position,
+            value,
+        )?;
+        tile.piece.place(
+            self.geometry.cell_offset(position),
+            self.geometry.cell_size(),
+        )?;
+        self.tiles.push(tile);
+        Ok(self.tiles.last_mut().unwrap())
+    }
+
+    fn find_tile(&self, position: Position) -> Option<usize> {
+        self.tiles.iter().position(|tile| tile.position == position)
+    }
+
+    /// Jumps to the end of the animations of the last move
+    fn fast_forward(&mut self) -> panelgui::Result<()> {
+        for id in self.animations.drain(..) {
+            self.frame.finish_animation(id)?;
+        }
+        Ok(())
+    }
+
+    /// Resolves when the animations of the last move are over
+    fn finished(&self) -> panelgui::Result<impl Future<Output = ()> + Send + 'static> {
+        self.frame.animations_finished(&self.animations)
+    }
+
+    /// Replaces all tiles, e.g. when a new game starts. The tiles fade in
+    fn reset(&mut self, tiles: Vec<(Position, u32)>) -> panelgui::Result<()> {
+        self.fast_forward()?;
+        self.merged.clear();
+        self.tiles.clear();
+        for (position, value) in tiles {
+            self.spawn(position, value)?;
+        }
+        Ok(())
+    }
+
+    fn spawn(&mut self, position: Position, value: u32) -> panelgui::Result<()> {
+        let frame = self.frame.clone();
+        let tile = self.add_tile(position, value)?;
+        tile.piece.container.SetOpacity(0.)?;
+        let id = frame.animate(
+            AnimationTarget::Opacity(tile.piece.container.clone().into()),
+            AnimationValue::Opacity(1.),
+            FADE_IN,
+        )?;
+        self.animations.push(id);
+        Ok(())
+    }
+
+    fn slide(&mut self, index: usize, to: Position) -> panelgui::Result<()> {
+        let tile = &mut self.tiles[index];
+        tile.position = to;
+        let id = self.frame.animate(
+            AnimationTarget::Offset(tile.piece.container.clone().into()),
+            AnimationValue::Offset(self.geometry.cell_offset(to)),
+            SLIDE,
+        )?;
+        self.animations.push(id);
+        Ok(())
+    }
+
+    /// Animates the move: tiles slide to their new cells, merged tiles pop up
+    /// and the spawned tile fades in. Animations of the previous move which are still
+    /// running are fast-forwarded, so that tiles never go out of sync with the game
+    fn apply(&mut self, diff: MoveDiff) -> panelgui::Result<()> {
+        tracing::debug!(direction = ?diff.direction, changes = diff.changes.len(), "board move");
+        self.fast_forward()?;
+        self.merged.clear();
+        // Tiles are looked up by their positions before the move, as one tile
+        // can move to the cell another tile is leaving
+        let mut sources = Vec::new();
+        for change in &diff.changes {
+            let from = match *change {
+                TileChange::Moved { from, .. } => vec![from],
+                TileChange::Merged { from, .. } => vec![from.0, from.1],
+                TileChange::Spawned { .. } => Vec::new(),
+            };
+            let indices = from
+                .into_iter()
+                .filter_map(|position| {
+                    let index = self.find_tile(position);
+                    if index.is_none() {
+                        tracing::warn!(position = ?position, "no tile on the board");
+                    }
+                    index
+                })
+                .collect::<Vec<_>>();
+            sources.push(indices);
+        }
+        let mut removed = Vec::new();
+        for (change, indices) in diff.changes.iter().zip(sources) {
+            match *change {
+                TileChange::Moved { to, .. } => {
+                    for index in indices {
+                        self.slide(index, to)?;
+                    }
+                }
+                TileChange::Merged { to, value, .. } => {
+                    for index in indices {
+                        self.slide(index, to)?;
+                        removed.push(index);
+                    }
+                    let frame = self.frame.clone();
+                    let tile = self.add_tile(to, value)?;
+                    tile.piece.container.SetScale(HIDDEN)?;
+                    let id = frame.animate(
+                        AnimationTarget::Scale(tile.piece.container.clone().into()),
+                        AnimationValue::Scale(VISIBLE),
+                        POP,
+                    )?;
+                    self.animations.push(id);
+                }
+                TileChange::Spawned { at, value } => self.spawn(at, value)?,
+            }
+        }
+        // Removing from the end keeps the remaining indices valid
+        removed.sort_unstable();
+        for index in removed.into_iter().rev() {
+            let tile = self.tiles.remove(index);
+            self.merged.push(tile);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct BoardKeeper(Keeper<Board>);
+
+impl BoardKeeper {
+    pub fn new(
+        frame: FrameTag,
+        slot: SlotTag,
+        width: usize,
+        height: usize,
+    ) -> panelgui::Result<Self> {
+        let keeper = Self(Keeper::new(Board::new(frame, slot, width, height)?));
+        keeper.spawn_event_handlers()?;
+        Ok(keeper)
+    }
+    pub fn tag(&self) -> BoardTag {
+        BoardTag(self.0.tag())
+    }
+    pub fn get(&self) -> RwLockReadGuard<'_, Board> {
+        self.0.get()
+    }
+    pub fn get_mut(&self) -> RwLockWriteGuard<'_, Board> {
+        self.0.get_mut()
+    }
+    /// Resolves when event handlers of the board are stopped, i.e. after all keepers are dropped
+    pub fn tasks_finished(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.get().tasks.finished()
+    }
+    fn spawn_event_handlers(&self) -> panelgui::Result<()> {
+        let tag = self.tag();
+        let slot = self.get().slot.tag();
+        {
+            let tag = tag.clone();
+            let slot = slot.clone();
+            self.get_mut().tasks.spawn("Board::on_size", async move {
+                while let Some(size) = slot.on_size().next().await {
+                    tag.set_size(size.0)?;
+                }
+                Ok(())
+            })?;
+        }
+        self.get_mut()
+            .tasks
+            .spawn("Board::on_theme_changed", async move {
+                while let Some(event) = slot.on_theme_changed().next().await {
+                    tag.set_theme(event)?;
+                }
+                Ok(())
+            })
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct BoardTag(Tag<Board>);
+
+impl BoardTag {
+    pub fn reset(&self, tiles: Vec<(Position, u32)>) -> panelgui::Result<()> {
+        Ok(self.0.call_mut(|v| v.reset(tiles))??)
+    }
+    pub fn apply(&self, diff: MoveDiff) -> panelgui::Result<()> {
+        Ok(self.0.call_mut(|v| v.apply(diff))??)
+    }
+    /// Resolves when the animations of the last move (or reset) are over
+    pub fn finished(&self) -> panelgui::Result<impl Future<Output = ()> + Send + 'static> {
+        self.0.call(|v| v.finished())?
+    }
+    pub fn fast_forward(&self) -> panelgui::Result<()> {
+        self.0.call_mut(|v| v.fast_forward())?
+    }
+    fn set_theme(&self, event: ThemeChanged) -> panelgui::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_theme(event))??)
+    }
+    pub fn set_size(&self, size: Vector2) -> panelgui::Result<()> {
+        Ok(self.0.call_mut(|v| v.set_size(size))??)
+    }
+}
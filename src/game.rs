@@ -0,0 +1,334 @@
+//! Rules of the game, independent of the UI. Each move produces a `MoveDiff` describing
+//! what happened to every tile, so the board can animate it and tests or replays can check it
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl Position {
+    pub fn new(x: usize, y: usize) -> Self {
+        Self { x, y }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Up,
+    Right,
+    Down,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TileChange {
+    Moved {
+        from: Position,
+        to: Position,
+        value: u32,
+    },
+    /// Two tiles joined into one with the doubled `value`. The first source is the tile
+    /// closer to the wall the tiles were pushed to. It may stay in place, i.e. be equal to `to`
+    Merged {
+        from: (Position, Position),
+        to: Position,
+        value: u32,
+    },
+    Spawned {
+        at: Position,
+        value: u32,
+    },
+}
+
+/// Result of a move. Tiles which neither moved nor merged are not mentioned
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MoveDiff {
+    pub direction: Direction,
+    pub changes: Vec<TileChange>,
+    /// Points got for the merges of this move
+    pub score: u32,
+}
+
+/// Pseudo-random generator (xorshift64*), so that a game can be repeated from its seed
+#[derive(Clone, Debug)]
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        // Zero state would produce zeros forever
+        Self(seed ^ 0x9E37_79B9_7F4A_7C15)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Game {
+    width: usize,
+    height: usize,
+    tiles: Vec<Option<u32>>,
+    score: u32,
+    random: Random,
+}
+
+impl Game {
+    /// New game with two spawned tiles. Games with the same seed go the same way
+    /// for the same moves
+    pub fn new(width: usize, height: usize, seed: u64) -> Self {
+        let mut game = Self {
+            width,
+            height,
+            tiles: vec![None; width * height],
+            score: 0,
+            random: Random::new(seed),
+        };
+        game.spawn();
+        game.spawn();
+        game
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn tile(&self, position: Position) -> Option<u32> {
+        self.tiles[position.y * self.width + position.x]
+    }
+
+    /// All tiles on the board, row by row
+    pub fn tiles(&self) -> Vec<(Position, u32)> {
+        self.positions()
+            .filter_map(|position| self.tile(position).map(|value| (position, value)))
+            .collect()
+    }
+
+    pub fn max_tile(&self) -> u32 {
+        self.tiles.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    /// True when no move changes the board
+    pub fn is_over(&self) -> bool {
+        [
+            Direction::Left,
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+        ]
+        .iter()
+        .all(|&direction| !self.clone().slide(direction).0)
+    }
+
+    /// Pushes all tiles to the direction and spawns a new tile. Returns `None` and
+    /// leaves the game untouched if nothing can move that way
+    pub fn make_move(&mut self, direction: Direction) -> Option<MoveDiff> {
+        let mut next = self.clone();
+        let (changed, mut changes) = next.slide(direction);
+        if !changed {
+            return None;
+        }
+        let score = next.score - self.score;
+        changes.extend(next.spawn());
+        *self = next;
+        Some(MoveDiff {
+            direction,
+            changes,
+            score,
+        })
+    }
+
+    fn positions(&self) -> impl Iterator<Item = Position> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| Position::new(x, y)))
+    }
+
+    fn set_tile(&mut self, position: Position, value: Option<u32>) {
+        self.tiles[position.y * self.width + position.x] = value;
+    }
+
+    /// Lines of positions along the direction, each starting at the wall the tiles go to
+    fn lines(&self, direction: Direction) -> Vec<Vec<Position>> {
+        let (width, height) = (self.width, self.height);
+        match direction {
+            Direction::Left => (0..height)
+                .map(|y| (0..width).map(|x| Position::new(x, y)).collect())
+                .collect(),
+            Direction::Right => (0..height)
+                .map(|y| (0..width).rev().map(|x| Position::new(x, y)).collect())
+                .collect(),
+            Direction::Up => (0..width)
+                .map(|x| (0..height).map(|y| Position::new(x, y)).collect())
+                .collect(),
+            Direction::Down => (0..width)
+                .map(|x| (0..height).rev().map(|y| Position::new(x, y)).collect())
+                .collect(),
+        }
+    }
+
+    /// Moves and merges tiles without spawning. Returns whether anything changed.
+    /// Each tile merges at most once per move
+    fn slide(&mut self, direction: Direction) -> (bool, Vec<TileChange>) {
+        let mut changed = false;
+        let mut changes = Vec::new();
+        for line in self.lines(direction) {
+            // Resulting tiles of the line with the positions they are made of
+            let mut packed: Vec<(u32, Vec<Position>)> = Vec::new();
+            for &position in &line {
+                if let Some(value) = self.tile(position) {
+                    match packed.last_mut() {
+                        Some((last, sources)) if *last == value && sources.len() == 1 => {
+                            *last *= 2;
+                            sources.push(position);
+                        }
+                        _ => packed.push((value, vec![position])),
+                    }
+                }
+            }
+            for &position in &line {
+                self.set_tile(position, None);
+            }
+            for (&to, (value, sources)) in line.iter().zip(packed) {
+                self.set_tile(to, Some(value));
+                if let [first, second] = sources[..] {
+                    self.score += value;
+                    changed = true;
+                    changes.push(TileChange::Merged {
+                        from: (first, second),
+                        to,
+                        value,
+                    });
+                } else if sources[0] != to {
+                    changed = true;
+                    changes.push(TileChange::Moved {
+                        from: sources[0],
+                        to,
+                        value,
+                    });
+                }
+            }
+        }
+        (changed, changes)
+    }
+
+    /// Puts 2 (or 4 with 10% probability) to a random empty cell
+    fn spawn(&mut self) -> Option<TileChange> {
+        let empty = self
+            .positions()
+            .filter(|&position| self.tile(position).is_none())
+            .collect::<Vec<_>>();
+        if empty.is_empty() {
+            return None;
+        }
+        let at = empty[self.random.below(empty.len())];
+        let value = if self.random.below(10) == 0 { 4 } else { 2 };
+        self.set_tile(at, Some(value));
+        Some(TileChange::Spawned { at, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Direction, Game, Position, TileChange};
+
+    /// Game with the given rows, zero meaning an empty cell
+    fn game(rows: &[&[u32]], seed: u64) -> Game {
+        let mut game = Game::new(rows[0].len(), rows.len(), seed);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &value) in row.iter().enumerate() {
+                game.set_tile(Position::new(x, y), Some(value).filter(|&v| v != 0));
+            }
+        }
+        game
+    }
+
+    fn row(game: &Game, y: usize) -> Vec<u32> {
+        (0..game.width())
+            .map(|x| game.tile(Position::new(x, y)).unwrap_or(0))
+            .collect()
+    }
+
+    #[test]
+    fn tiles_merge_once_per_move() {
+        let mut game = game(&[&[2, 2, 2, 2], &[0, 0, 0, 0]], 1);
+        let diff = game.make_move(Direction::Left).unwrap();
+        assert_eq!(row(&game, 0), vec![4, 4, 0, 0]);
+        let merges = diff
+            .changes
+            .iter()
+            .filter(|change| matches!(change, TileChange::Merged { .. }))
+            .count();
+        assert_eq!(merges, 2);
+        assert_eq!(
+            diff.changes[0],
+            TileChange::Merged {
+                from: (Position::new(0, 0), Position::new(1, 0)),
+                to: Position::new(0, 0),
+                value: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn score_grows_by_merged_values() {
+        let mut game = game(&[&[4, 4, 2, 2], &[0, 0, 0, 0]], 1);
+        let diff = game.make_move(Direction::Right).unwrap();
+        assert_eq!(row(&game, 0), vec![0, 0, 8, 4]);
+        assert_eq!(diff.score, 12);
+        assert_eq!(game.score(), 12);
+    }
+
+    #[test]
+    fn move_without_changes_is_rejected() {
+        let mut game = game(&[&[2, 4, 0, 0], &[4, 2, 0, 0]], 1);
+        let before = game.tiles();
+        assert_eq!(game.make_move(Direction::Left), None);
+        assert_eq!(game.make_move(Direction::Up), None);
+        assert_eq!(game.tiles(), before);
+        assert_eq!(game.score(), 0);
+        assert!(game.make_move(Direction::Right).is_some());
+    }
+
+    #[test]
+    fn same_seed_gives_same_spawns() {
+        let mut first = Game::new(4, 4, 42);
+        let mut second = Game::new(4, 4, 42);
+        assert_eq!(first.tiles(), second.tiles());
+        for &direction in [
+            Direction::Left,
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+        ]
+        .iter()
+        .cycle()
+        .take(20)
+        {
+            assert_eq!(first.make_move(direction), second.make_move(direction));
+            assert_eq!(first.tiles(), second.tiles());
+        }
+    }
+
+    #[test]
+    fn game_is_over_when_nothing_moves() {
+        assert!(game(&[&[2, 4], &[4, 2]], 1).is_over());
+        assert!(!game(&[&[2, 4], &[2, 8]], 1).is_over());
+        assert!(!game(&[&[2, 4], &[8, 0]], 1).is_over());
+    }
+}
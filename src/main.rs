@@ -2,29 +2,92 @@
 
 // use std::time::Duration;
 
+mod board;
+mod game;
 mod interop;
 mod wide_strings;
 mod window;
-use std::time::Duration;
-
-use async_std::task;
+use std::{
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
 
 use bindings::Windows::{
     Foundation::Numerics::{Vector2, Vector3},
     Win32::{
         Foundation::HWND,
         System::WinRT::{RoInitialize, RO_INIT_SINGLETHREADED},
-        UI::WindowsAndMessaging::{DispatchMessageW, GetMessageW, TranslateMessage, MSG},
+        UI::WindowsAndMessaging::{DispatchMessageW, GetMessageW, SetTimer, TranslateMessage, MSG},
     },
-    UI::Colors,
 };
-use futures::{executor::ThreadPool, StreamExt};
+use board::BoardKeeper;
+use futures::{executor::ThreadPool, stream, StreamExt};
+use game::{Direction, Game};
 use interop::create_dispatcher_queue_controller_for_current_thread;
 use panelgui::{
-    BackgroundKeeper, CellLimit, FrameKeeper, ReceiveSlotEvent, RibbonKeeper, RibbonOrientation,
+    path::Path, theme_file::ThemeWatcher, tree::TreeWatcher, Dialog, Executor, FrameKeeper,
+    FrameTag, InspectorKeeper, Key, KeyPressed, ManualExecutor, ReceiveSlotEvent, ShapeKeeper,
+    SlotKeeper, TextTag, Theme,
 };
 use window::Window;
 
+/// Description of the screen, can be overridden by GAME2049_UI
+const UI_FILE: &str = "ui/main.ron";
+
+const VK_F2: u32 = 0x71;
+const VK_N: u32 = 0x4E;
+const VK_F11: u32 = 0x7A;
+const VK_F12: u32 = 0x7B;
+
+const BOARD_SIZE: usize = 4;
+
+const FRAME_TIMER_ID: usize = 1;
+/// About 60 frames per second
+const FRAME_INTERVAL_MS: u32 = 16;
+
+/// Input of the game loop
+enum Input {
+    Key(KeyPressed),
+    /// The description of the screen was reloaded
+    Reload,
+}
+
+/// Board showing the game in the cell named "board" of the described screen
+fn plug_board(frame: &FrameTag, ui: &TreeWatcher, game: &Game) -> panelgui::Result<BoardKeeper> {
+    let slot = ui.tree().slot("board").ok_or_else(|| {
+        panelgui::Error::Description("no cell named \"board\" for the game board".to_string())
+    })?;
+    let board = BoardKeeper::new(frame.clone(), slot, BOARD_SIZE, BOARD_SIZE)?;
+    board.tag().reset(game.tiles())?;
+    Ok(board)
+}
+
+/// Description of the screen in the directory of the executable or above it,
+/// so it's found both next to an installed executable and from cargo's target directory
+fn find_ui_file() -> panelgui::Result<PathBuf> {
+    let exe = std::env::current_exe()
+        .map_err(|error| panelgui::Error::Description(format!("no executable path: {}", error)))?;
+    exe.ancestors()
+        .skip(1)
+        .map(|dir| dir.join(UI_FILE))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            panelgui::Error::Description(format!(
+                "{} not found near {}, set GAME2049_UI",
+                UI_FILE,
+                exe.display()
+            ))
+        })
+}
+
+/// Seed of a new game, so that each game goes differently
+fn new_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64)
+}
+
 fn run() -> panelgui::Result<()> {
     unsafe { RoInitialize(RO_INIT_SINGLETHREADED)? };
     let _controler = create_dispatcher_queue_controller_for_current_thread()?;
@@ -37,29 +100,127 @@ fn run() -> panelgui::Result<()> {
         Y: window_height as f32,
     };
 
-    // let local_pool = LocalPool::new();
-    let thread_pool = ThreadPool::builder().create().unwrap();
+    // Manual executor runs all event handlers on the window thread in deterministic order
+    let executor: Executor = if std::env::var("GAME2049_MANUAL_EXECUTOR").is_ok() {
+        ManualExecutor::new().into()
+    } else {
+        ThreadPool::builder().create().unwrap().into()
+    };
 
-    let frame_keeper = FrameKeeper::new(thread_pool.clone())?;
+    let frame_keeper = FrameKeeper::new(executor.clone())?;
     let frame = frame_keeper.tag();
     frame.frame_visual()?.SetSize(window_size)?;
 
+    let mut errors = frame.on_error()?;
+    executor.spawn_ok(async move {
+        while let Some(error) = errors.next().await {
+            tracing::error!(
+                origin = %error.origin,
+                slot = ?error.slot,
+                error = %error.error,
+                "task failed"
+            );
+        }
+    });
+
+    // Theme file is watched, so the game can be restyled while running
+    let _theme = match std::env::var("GAME2049_THEME") {
+        Ok(path) => Some(ThemeWatcher::new(frame.clone(), path)?),
+        Err(_) => None,
+    };
+
     let frame_slot = frame.open_slot()?;
-    let _background_keeper =
-        BackgroundKeeper::new(frame.clone(), frame_slot.clone(), Colors::White()?, false)?;
-    let ribbon_keeper = RibbonKeeper::new(
-        frame.clone(),
-        frame_slot.clone(),
-        RibbonOrientation::Horizontal,
-    )?;
-    let ribbon = ribbon_keeper.tag();
-    let left = ribbon.add_cell(CellLimit::default())?;
-    let center = ribbon.add_cell(CellLimit::new(2.0, Vector2 { X: 1.0, Y: 1.0 }, 300., None))?;
-    let right = ribbon.add_cell(CellLimit::default())?;
-    let _left_bkg_keeper = BackgroundKeeper::new(frame.clone(), left, Colors::Red()?, true)?;
-    let _center_bkg_keeper =
-        BackgroundKeeper::new(frame.clone(), center.clone(), Colors::Green()?, true)?;
-    let _right_bkg_keeper = BackgroundKeeper::new(frame.clone(), right, Colors::Blue()?, true)?;
+    let ui_path = match std::env::var_os("GAME2049_UI") {
+        Some(path) => PathBuf::from(path),
+        None => find_ui_file()?,
+    };
+    let ui = TreeWatcher::new(frame.clone(), frame_slot.clone(), ui_path)?;
+
+    let mut game = Game::new(BOARD_SIZE, BOARD_SIZE, new_seed());
+    let mut board_keeper = plug_board(&frame, &ui, &game)?;
+    let mut score = ui.tree().text("score");
+    // A move made while the previous one is animated fast-forwards its animation.
+    // With GAME2049_QUEUE_INPUT set it waits for the animation to end instead
+    let queue_input = std::env::var("GAME2049_QUEUE_INPUT").is_ok();
+    frame.thread_spawn("game", {
+        let frame = frame.clone();
+        let slot = frame_slot.clone();
+        async move {
+            let show_score = |score: &Option<TextTag>, value: u32| -> panelgui::Result<()> {
+                if let Some(score) = score {
+                    score.set_text(format!("Score: {}", value))?;
+                }
+                Ok(())
+            };
+            let mut board = board_keeper.tag();
+            // The stream is kept between moves, so keys pressed during animations are not lost
+            let mut inputs = stream::select(
+                slot.on_key_pressed().map(Input::Key),
+                ui.on_reload().map(|()| Input::Reload),
+            );
+            while let Some(input) = inputs.next().await {
+                let event = match input {
+                    Input::Key(event) => event,
+                    Input::Reload => {
+                        // The board and the score lived in the replaced tree
+                        board_keeper = plug_board(&frame, &ui, &game)?;
+                        board = board_keeper.tag();
+                        score = ui.tree().text("score");
+                        show_score(&score, game.score())?;
+                        continue;
+                    }
+                };
+                let mut restart = false;
+                let direction = match event.key {
+                    Key::Left => Some(Direction::Left),
+                    Key::Up => Some(Direction::Up),
+                    Key::Right => Some(Direction::Right),
+                    Key::Down => Some(Direction::Down),
+                    Key::Other(VK_N) => {
+                        let dialog = Dialog::message(
+                            frame.clone(),
+                            "Start new game?",
+                            vec![("Yes".to_string(), true), ("No".to_string(), false)],
+                        )?;
+                        restart = dialog.result().await? == Some(true);
+                        None
+                    }
+                    _ => continue,
+                };
+                if let Some(diff) = direction.and_then(|direction| game.make_move(direction)) {
+                    tracing::debug!(diff = ?diff, "game move");
+                    board.apply(diff)?;
+                    show_score(&score, game.score())?;
+                    if game.is_over() {
+                        tracing::info!(
+                            score = game.score(),
+                            max_tile = game.max_tile(),
+                            "game over"
+                        );
+                        // The last move is shown before the dialog covers the board
+                        board.finished()?.await;
+                        let dialog = Dialog::message(
+                            frame.clone(),
+                            &format!("Game over! Score: {}", game.score()),
+                            vec![("New game".to_string(), true)],
+                        )?;
+                        // Closing the dialog with Escape starts a new game as well,
+                        // the finished game can't be continued anyway
+                        dialog.result().await?;
+                        restart = true;
+                    } else if queue_input {
+                        board.finished()?.await;
+                    }
+                }
+                if restart {
+                    game = Game::new(BOARD_SIZE, BOARD_SIZE, new_seed());
+                    board.reset(game.tiles())?;
+                    show_score(&score, game.score())?;
+                }
+            }
+            Ok(())
+        }
+    })?;
 
     // frame.spawn_local({
     //     let frame = frame.clone();
@@ -78,32 +239,35 @@ fn run() -> panelgui::Result<()> {
     //     }
     // })?;
 
-    frame.thread_spawn({
+    frame.thread_spawn("click markers", {
         let compositor = frame.compositor()?;
         let frame = frame.clone();
         let slot = frame_slot.clone();
         async move {
             while let Some(event) = slot.on_mouse_left_pressed().next().await {
-                let compositor = compositor.clone();
                 let frame_visual = frame.frame_visual()?;
-                frame.thread_spawn(async move {
-                    let visual = compositor.CreateShapeVisual()?;
-                    let geometry = compositor.CreateEllipseGeometry()?;
-                    geometry.SetRadius(Vector2 { X: 10., Y: 10. })?;
-                    geometry.SetCenter(Vector2 { X: 10., Y: 10. })?;
-                    let brush = compositor.CreateColorBrushWithColor(Colors::Yellow()?)?;
-                    let sprite = compositor.CreateSpriteShapeWithGeometry(geometry)?;
-                    sprite.SetFillBrush(brush)?;
-                    visual.Shapes()?.Append(sprite)?;
-                    visual.SetOffset(Vector3 {
-                        X: event.0.X - 10.,
-                        Y: event.0.Y - 10.,
-                        Z: 0.,
-                    })?;
-                    visual.SetSize(Vector2 { X: 20., Y: 20. })?;
-                    frame_visual.Children()?.InsertAtTop(visual.clone())?;
-                    task::sleep(Duration::from_secs(5)).await;
-                    frame_visual.Children()?.Remove(visual)?;
+                let container = compositor.CreateContainerVisual()?;
+                container.SetOffset(Vector3 {
+                    X: event.0.X - 10.,
+                    Y: event.0.Y - 10.,
+                    Z: 0.,
+                })?;
+                container.SetSize(Vector2 { X: 20., Y: 20. })?;
+                let marker_slot = SlotKeeper::new(container.clone())?;
+                let marker = ShapeKeeper::new(
+                    frame.clone(),
+                    marker_slot.tag(),
+                    Path::ellipse((10., 10.), (10., 10.)),
+                    Some("marker".into()),
+                    None,
+                )?;
+                frame_visual.Children()?.InsertAtTop(container.clone())?;
+                let expired = frame.timer(Duration::from_secs(5))?;
+                frame.thread_spawn("click marker", async move {
+                    expired.await;
+                    drop(marker);
+                    drop(marker_slot);
+                    frame_visual.Children()?.Remove(container)?;
                     Ok(())
                 })?;
             }
@@ -111,20 +275,66 @@ fn run() -> panelgui::Result<()> {
         }
     })?;
 
-    let window = Window::new(
-        "2049-rs",
-        window_width,
-        window_height,
-        // local_pool,
-        frame.clone(),
-    )?;
+    // F2 switches between light, dark and high contrast themes
+    frame.thread_spawn("theme switch", {
+        let frame = frame.clone();
+        let slot = frame_slot.clone();
+        async move {
+            let themes = [Theme::light, Theme::dark, Theme::high_contrast];
+            let mut current = 0;
+            while let Some(event) = slot.on_key_pressed().next().await {
+                if event.key == Key::Other(VK_F2) {
+                    current = (current + 1) % themes.len();
+                    frame.set_theme(themes[current]())?;
+                }
+            }
+            Ok(())
+        }
+    })?;
+
+    // F12 shows outlines of the slots, F11 writes the slot tree to a file as text,
+    // Shift+F11 as JSON
+    let inspector = InspectorKeeper::new(frame.clone())?;
+    frame.thread_spawn("inspector", {
+        let frame = frame.clone();
+        let slot = frame_slot.clone();
+        let inspector = inspector.tag();
+        async move {
+            let mut keys = slot.on_key_pressed();
+            while let Some(event) = keys.next().await {
+                match event.key {
+                    Key::Other(VK_F12) => {
+                        inspector.toggle()?;
+                    }
+                    Key::Other(VK_F11) => {
+                        let info = frame.inspect()?;
+                        let (path, dump) = if event.shift {
+                            ("game2049-rs-tree.json", info.json())
+                        } else {
+                            ("game2049-rs-tree.txt", info.text())
+                        };
+                        match std::fs::write(path, dump) {
+                            Ok(()) => tracing::info!(path, "slot tree written"),
+                            Err(error) => {
+                                tracing::error!(path, error = %error, "slot tree not written")
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(())
+        }
+    })?;
+
+    let window = Window::new("2049-rs", window_width, window_height, frame.clone())?;
     let target = window.create_window_target(&frame.compositor()?, false)?;
     target.SetRoot(frame.frame_visual()?)?;
 
     let mut message = MSG::default();
     unsafe {
-        // const IDT_TIMER1: usize = 1;
-        // SetTimer(window.handle(), IDT_TIMER1, 10, None);
+        // The timer drives the frame's clock, i.e. animations and timers
+        SetTimer(window.handle(), FRAME_TIMER_ID, FRAME_INTERVAL_MS, None);
         while GetMessageW(&mut message, HWND(0), 0, 0).into() {
             TranslateMessage(&mut message);
             DispatchMessageW(&mut message);
@@ -134,11 +344,27 @@ fn run() -> panelgui::Result<()> {
     Ok(())
 }
 
+/// The application has no console, so diagnostics are written to a file.
+/// Logging is enabled by setting GAME2049_LOG to a filter, e.g. "panelgui=debug"
+fn init_tracing() {
+    if let Ok(filter) = std::env::var("GAME2049_LOG") {
+        if let Ok(file) = std::fs::File::create("game2049-rs.log") {
+            tracing_subscriber::fmt()
+                .with_env_filter(filter)
+                .with_writer(Mutex::new(file))
+                .with_ansi(false)
+                .init();
+        }
+    }
+}
+
 fn main() {
+    init_tracing();
     let result = run();
 
     // We do this for nicer HRESULT printing when errors occur.
     if let Err(error) = result {
+        tracing::error!(error = %error, "application failed");
         match error {
             // TODO - trace stack with error-chain
             panelgui::Error::Windows(error) => error.code().unwrap(),
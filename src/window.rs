@@ -6,29 +6,32 @@ use bindings::Windows::{
     Win32::{
         Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, PWSTR, RECT, WPARAM},
         System::{LibraryLoader::GetModuleHandleW, WinRT::ICompositorDesktopInterop},
+        UI::KeyboardAndMouseInput::GetKeyState,
         UI::WindowsAndMessaging::{
             AdjustWindowRectEx, CreateWindowExW, DefWindowProcW, GetClientRect, LoadCursorW,
             PostQuitMessage, RegisterClassW, ShowWindow, CREATESTRUCTW, CW_USEDEFAULT,
             GWLP_USERDATA, HMENU, IDC_ARROW, SW_SHOW, WINDOW_LONG_PTR_INDEX, WM_DESTROY,
-            WM_LBUTTONDOWN, WM_MOUSEMOVE, WM_NCCREATE, WM_RBUTTONDOWN, WM_SIZE, WM_SIZING,
-            WM_TIMER, WNDCLASSW, WS_EX_NOREDIRECTIONBITMAP, WS_OVERLAPPEDWINDOW,
+            WM_KEYDOWN, WM_LBUTTONDOWN, WM_MOUSEMOVE, WM_NCCREATE, WM_RBUTTONDOWN, WM_SIZE,
+            WM_SIZING, WM_TIMER, WNDCLASSW, WS_EX_NOREDIRECTIONBITMAP, WS_OVERLAPPEDWINDOW,
         },
     },
     UI::Composition::{Compositor, Desktop::DesktopWindowTarget},
 };
 
-use panelgui::{MouseLeftPressed, MouseLeftPressedFocused, SendSlotEvent, SlotSize};
+use panelgui::{
+    FrameTag, Key, KeyPressed, MouseLeftPressed, MouseLeftPressedFocused, SendSlotEvent, SlotSize,
+};
 use windows::{Handle, Interface};
 
 use crate::wide_strings::ToWide;
 
 static REGISTER_WINDOW_CLASS: Once = Once::new();
 static WINDOW_CLASS_NAME: &str = "game2049-rs.Window";
+const VK_SHIFT: usize = 0x10;
 
 pub struct Window {
     handle: HWND,
-    event_dst: Box<dyn SendSlotEvent>,
-    // pool: LocalPool,
+    frame: FrameTag,
     mouse_pos: Vector2,
 }
 
@@ -37,8 +40,7 @@ impl Window {
         title: &str,
         width: u32,
         height: u32,
-        // pool: LocalPool,
-        event_dst: impl SendSlotEvent + 'static,
+        frame: FrameTag,
     ) -> windows::Result<Box<Self>> {
         let class_name = WINDOW_CLASS_NAME.to_wide();
         let instance = unsafe { GetModuleHandleW(PWSTR(std::ptr::null_mut())).ok()? };
@@ -70,12 +72,10 @@ impl Window {
             }
             (rect.right - rect.left, rect.bottom - rect.top)
         };
-        let event_dst = Box::new(event_dst);
         let mouse_pos = Vector2::default();
         let mut result = Box::new(Self {
             handle: HWND(0),
-            event_dst,
-            // pool,
+            frame,
             mouse_pos,
         });
 
@@ -140,25 +140,36 @@ impl Window {
                     X: new_size.Width as f32,
                     Y: new_size.Height as f32,
                 };
-                self.event_dst.send_size(SlotSize(new_size)).unwrap();
+                self.frame.send_size(SlotSize(new_size)).unwrap();
             }
             WM_LBUTTONDOWN => {
-                self.event_dst
+                self.frame
                     .send_mouse_left_pressed(MouseLeftPressed(self.mouse_pos))
                     .unwrap();
-                self.event_dst
+                self.frame
                     .send_mouse_left_pressed_focused(MouseLeftPressedFocused(self.mouse_pos))
                     .unwrap();
             }
+            WM_KEYDOWN => {
+                if wparam.0 != VK_SHIFT {
+                    self.frame
+                        .send_key_pressed(KeyPressed {
+                            key: get_key(wparam),
+                            shift: is_shift_pressed(),
+                        })
+                        .unwrap();
+                }
+            }
             WM_RBUTTONDOWN => {
                 // self.game.on_pointer_pressed(true, false).unwrap();
             }
             WM_TIMER => {
-                // dbg!("timer");
+                self.frame.tick().unwrap();
             }
             _ => {}
         }
-        // self.pool.run_until_stalled();
+        // Let event handlers run if the frame uses manual executor
+        self.frame.run_until_stalled().unwrap();
         unsafe { DefWindowProcW(self.handle, message, wparam, lparam) }
     }
 
@@ -204,6 +215,27 @@ fn get_mouse_position(lparam: LPARAM) -> (isize, isize) {
     (x, y)
 }
 
+/// State of Shift at the time of the current message. It's queried rather than tracked,
+/// as releases outside of the window are not seen
+fn is_shift_pressed() -> bool {
+    // The high bit is set while the key is down
+    unsafe { GetKeyState(VK_SHIFT as i32) < 0 }
+}
+
+fn get_key(wparam: WPARAM) -> Key {
+    match wparam.0 {
+        0x09 => Key::Tab,
+        0x0D => Key::Enter,
+        0x1B => Key::Escape,
+        0x20 => Key::Space,
+        0x25 => Key::Left,
+        0x26 => Key::Up,
+        0x27 => Key::Right,
+        0x28 => Key::Down,
+        code => Key::Other(code as u32),
+    }
+}
+
 #[allow(non_snake_case)]
 #[cfg(target_pointer_width = "32")]
 unsafe fn SetWindowLong(window: HWND, index: WINDOW_LONG_PTR_INDEX, value: isize) -> isize {